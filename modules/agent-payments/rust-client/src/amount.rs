@@ -0,0 +1,355 @@
+//! The wire format for wei amounts.
+//!
+//! Amounts are accepted as a JSON string of decimal digits, a `0x`-prefixed hex
+//! string, or a non-negative JSON integer. Leading zeros are normalized away
+//! rather than rejected, so `"0010"` is 10. Anything else — scientific notation,
+//! signs, fractions, whitespace, digit separators — is an error instead of a
+//! silent fallback. Numbers typed in a locale format (`1.000,5`, `1 000`,
+//! `1,5 eth`) get an error naming every reading we refused to pick between,
+//! see [`locale_format`]. Amounts are always written back with [`format_amount`], so
+//! every amount in an Output parses to the same value and prints to the same
+//! string. Human-readable renderings (CSV `amount_eth`, the `format_amount`
+//! command) all go through [`format_display`].
+
+use crate::error::coded;
+use alloy::primitives::U256;
+use anyhow::Result;
+use serde_json::Value;
+
+pub fn parse_amount(value: &Value, field: &str) -> Result<U256> {
+    match value {
+        Value::String(text) => parse_amount_str(text, field),
+        Value::Number(number) => number.as_u64().map(U256::from).ok_or_else(|| {
+            invalid(field, &number.to_string(), "JSON numbers must be non-negative integers; pass large amounts as strings")
+        }),
+        Value::Null => Err(coded("INVALID_AMOUNT", format!("{} is required", field))),
+        other => Err(invalid(field, &other.to_string(), "expected a string or integer")),
+    }
+}
+
+/// Like [`parse_amount`], but an absent field takes `default`.
+pub fn parse_amount_or(value: &Value, field: &str, default: U256) -> Result<U256> {
+    if value.is_null() {
+        Ok(default)
+    } else {
+        parse_amount(value, field)
+    }
+}
+
+pub fn parse_amount_str(text: &str, field: &str) -> Result<U256> {
+    if let Some(reason) = locale_format(text) {
+        return Err(invalid(field, text, &reason));
+    }
+    let (digits, radix) = match text.strip_prefix("0x") {
+        Some(hex) => (hex, 16),
+        None => (text, 10),
+    };
+    if digits.is_empty() {
+        return Err(invalid(field, text, "no digits"));
+    }
+    // from_str_radix tolerates `_` separators, so check the digits ourselves first
+    if let Some(bad) = digits.chars().find(|c| !c.is_digit(radix)) {
+        let reason = match bad {
+            'e' | 'E' if radix == 10 => "scientific notation is not accepted",
+            '+' | '-' => "signs are not accepted",
+            '.' => "fractional amounts are not accepted",
+            _ => "unexpected character",
+        };
+        return Err(invalid(field, text, reason));
+    }
+    U256::from_str_radix(digits, u64::from(radix)).map_err(|_| invalid(field, text, "exceeds uint256"))
+}
+
+/// Grouping characters seen in pasted amounts: space, no-break, thin and narrow no-break
+/// spaces, and the Swiss apostrophe. None of them is ever part of an accepted amount.
+const GROUPING_SPACES: &[char] = &[' ', '\u{a0}', '\u{2009}', '\u{202f}', '\''];
+
+/// Why `text` looks like a number in a locale format, listing each plain reading of it, or
+/// `None` when it does not. Never picks a reading: `1,000` may be a thousand or one.
+pub fn locale_format(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    let letters = trimmed.len() - trimmed.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+    let (number, unit) = trimmed.split_at(trimmed.len() - letters);
+    let number = number.trim_end();
+    let unit = match unit {
+        "" => "",
+        unit if Unit::parse(unit).is_ok() => unit,
+        _ => return None,
+    };
+
+    let digit_ends = number.starts_with(|c: char| c.is_ascii_digit()) && number.ends_with(|c: char| c.is_ascii_digit());
+    let localized = number.contains(',') || number.contains(GROUPING_SPACES) || number.matches('.').count() > 1;
+    let numeric = number.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',' || GROUPING_SPACES.contains(&c));
+    if !digit_ends || !localized || !numeric {
+        return None;
+    }
+
+    let ungrouped: String = number.chars().filter(|c| !GROUPING_SPACES.contains(c)).collect();
+    let mut readings: Vec<String> = Vec::new();
+    for (group, decimal) in [('.', ','), (',', '.')] {
+        if let Some(reading) = plain_reading(&ungrouped, group, decimal) {
+            if !readings.contains(&reading) {
+                readings.push(reading);
+            }
+        }
+    }
+    if !unit.is_empty() {
+        readings.iter_mut().for_each(|reading| *reading = format!("{} {}", reading, unit));
+    }
+    let seen = match readings.as_slice() {
+        [] => "digit grouping and decimal commas are not accepted".to_string(),
+        [only] => format!("looks like {} written with locale separators", only),
+        [first, second, ..] => format!("ambiguous: could be {} or {}", first, second),
+    };
+    Some(format!("{}; write plain digits with '.' as the only decimal point", seen))
+}
+
+/// `text` with `group` as the thousands separator and `decimal` as the decimal mark, in plain
+/// form, or `None` if it is not well formed that way (groups of three after the first).
+fn plain_reading(text: &str, group: char, decimal: char) -> Option<String> {
+    let (whole, fraction) = match text.split_once(decimal) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (text, None),
+    };
+    let groups: Vec<&str> = whole.split(group).collect();
+    let grouped_well = groups.iter().enumerate().all(|(i, digits)| {
+        let sized = if i == 0 { (1..=3).contains(&digits.len()) || groups.len() == 1 } else { digits.len() == 3 };
+        sized && !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    });
+    if !grouped_well || fraction.is_some_and(|f| f.is_empty() || !f.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let whole = groups.concat();
+    Some(match fraction {
+        Some(fraction) => format!("{}.{}", whole, fraction),
+        None => whole,
+    })
+}
+
+/// Canonical decimal form: no prefix, no sign, no leading zeros.
+pub fn format_amount(amount: U256) -> String {
+    amount.to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Eth,
+    Gwei,
+    Wei,
+}
+
+impl Unit {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "eth" | "ether" => Ok(Unit::Eth),
+            "gwei" => Ok(Unit::Gwei),
+            "wei" => Ok(Unit::Wei),
+            _ => Err(coded("INVALID_AMOUNT", format!("Unknown unit '{}': expected eth, gwei or wei", name))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Eth => "eth",
+            Unit::Gwei => "gwei",
+            Unit::Wei => "wei",
+        }
+    }
+
+    fn decimals(self) -> u32 {
+        match self {
+            Unit::Eth => 18,
+            Unit::Gwei => 9,
+            Unit::Wei => 0,
+        }
+    }
+}
+
+/// How [`format_display`] renders an amount. The default is every decimal of ETH, as in CSV exports.
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    pub unit: Unit,
+    /// Rounds half up to this many decimals; `None` keeps all of them.
+    pub max_decimals: Option<u32>,
+    pub thousands_separators: bool,
+    pub trim_trailing_zeros: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            unit: Unit::Eth,
+            max_decimals: None,
+            thousands_separators: false,
+            trim_trailing_zeros: false,
+        }
+    }
+}
+
+pub fn format_display(amount: U256, options: &DisplayOptions) -> String {
+    let ten = U256::from(10u64);
+    let decimals = options.unit.decimals();
+    let kept = options.max_decimals.map_or(decimals, |max| max.min(decimals));
+
+    let scale = ten.pow(U256::from(decimals));
+    let (mut whole, fraction) = (amount / scale, amount % scale);
+    let dropped = ten.pow(U256::from(decimals - kept));
+    let mut fraction_kept = fraction / dropped;
+    if (fraction % dropped) * U256::from(2u64) >= dropped && dropped > U256::from(1u64) {
+        fraction_kept += U256::from(1u64);
+        // Carry into the whole part; it is at most U256::MAX / 10^decimals here
+        if fraction_kept == ten.pow(U256::from(kept)) {
+            fraction_kept = U256::ZERO;
+            whole += U256::from(1u64);
+        }
+    }
+
+    let mut text = whole.to_string();
+    if options.thousands_separators {
+        text = group_thousands(&text);
+    }
+    if kept > 0 {
+        let mut digits = format!("{:0>width$}", fraction_kept.to_string(), width = kept as usize);
+        if options.trim_trailing_zeros {
+            digits.truncate(digits.trim_end_matches('0').len());
+        }
+        if !digits.is_empty() {
+            text.push('.');
+            text.push_str(&digits);
+        }
+    }
+    text
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+fn invalid(field: &str, text: &str, reason: &str) -> anyhow::Error {
+    coded("INVALID_AMOUNT", format!("Invalid {} '{}': {}", field, text, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::error_code;
+    use serde_json::json;
+
+    fn parse(value: Value) -> Result<U256> {
+        parse_amount(&value, "amount")
+    }
+
+    #[test]
+    fn rejects_strings_that_used_to_parse_wrongly() {
+        for text in ["1e18", "1E18", "+1", "-1", "1.0", "1.5e3", " 1", "1 ", "1_000", "1,000", "0x", "", "0X10", "0b1"] {
+            let err = parse(json!(text)).unwrap_err();
+            assert_eq!(error_code(&err).as_deref(), Some("INVALID_AMOUNT"), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn locale_formats_are_refused_with_every_reading() {
+        let cases = [
+            ("1.000,5", "looks like 1000.5 written"),
+            ("1,000", "ambiguous: could be 1.000 or 1000"),
+            ("1,000.50", "looks like 1000.50 written"),
+            ("1,5", "looks like 1.5 written"),
+            ("1,5 eth", "looks like 1.5 eth written"),
+            ("10,5gwei", "looks like 10.5 gwei written"),
+            ("1.000.000", "looks like 1000000 written"),
+            ("1 000 000", "looks like 1000000 written"),
+            ("1\u{202f}000,25", "looks like 1000.25 written"),
+            ("1\u{a0}234", "looks like 1234 written"),
+            ("1'000'000", "looks like 1000000 written"),
+            ("12,34,567", "digit grouping and decimal commas are not accepted"),
+        ];
+        for (text, reason) in cases {
+            let err = parse(json!(text)).unwrap_err();
+            assert_eq!(error_code(&err).as_deref(), Some("INVALID_AMOUNT"));
+            assert!(err.to_string().contains(reason), "{:?}: {}", text, err);
+        }
+        for text in ["1.5", "1000", "1e18", "0x10", "1,5 apples", " 1"] {
+            assert_eq!(locale_format(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_non_integer_json_numbers() {
+        for value in [json!(1e18), json!(-1), json!(1.5), json!(true), json!([1])] {
+            assert!(parse(value.clone()).is_err(), "{} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn rejects_values_beyond_uint256() {
+        let too_big = format!("{}0", U256::MAX);
+        assert!(parse(json!(too_big)).is_err());
+    }
+
+    #[test]
+    fn normalizes_leading_zeros() {
+        assert_eq!(parse(json!("0001000000000000000000")).unwrap(), U256::from(10u64).pow(U256::from(18u64)));
+        assert_eq!(parse(json!("0000")).unwrap(), U256::ZERO);
+        assert_eq!(format_amount(parse(json!("007")).unwrap()), "7");
+    }
+
+    #[test]
+    fn accepts_hex_and_integers() {
+        assert_eq!(parse(json!("0xde0b6b3a7640000")).unwrap(), U256::from(1_000_000_000_000_000_000u64));
+        assert_eq!(parse(json!(42)).unwrap(), U256::from(42u64));
+    }
+
+    #[test]
+    fn formatted_amounts_round_trip_exactly() {
+        for amount in [U256::ZERO, U256::from(1u64), U256::from(10u64).pow(U256::from(18u64)), U256::MAX] {
+            let text = format_amount(amount);
+            assert_eq!(parse(json!(text.clone())).unwrap(), amount);
+            assert_eq!(format_amount(parse(json!(text.clone())).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn missing_amount_uses_default_only_when_asked() {
+        assert!(parse(Value::Null).is_err());
+        assert_eq!(parse_amount_or(&Value::Null, "amount", U256::from(5u64)).unwrap(), U256::from(5u64));
+        assert!(parse_amount_or(&json!("1e3"), "amount", U256::ZERO).is_err());
+    }
+
+    #[test]
+    fn display_matches_format_units_by_default() {
+        for amount in [U256::ZERO, U256::from(1u64), U256::from(1_234_567_890_123_456_789u64), U256::MAX] {
+            assert_eq!(
+                format_display(amount, &DisplayOptions::default()),
+                alloy::primitives::utils::format_units(amount, 18).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn display_rounds_groups_and_trims() {
+        let amount = U256::from(1_234_567_890_123_456_789u64);
+        let options = |unit, max_decimals, thousands_separators, trim_trailing_zeros| DisplayOptions {
+            unit,
+            max_decimals,
+            thousands_separators,
+            trim_trailing_zeros,
+        };
+        assert_eq!(format_display(amount, &options(Unit::Eth, Some(4), false, false)), "1.2346");
+        assert_eq!(format_display(amount, &options(Unit::Gwei, Some(2), true, false)), "1,234,567,890.12");
+        assert_eq!(format_display(amount, &options(Unit::Wei, Some(2), true, false)), "1,234,567,890,123,456,789");
+        assert_eq!(format_display(amount, &options(Unit::Eth, Some(0), false, false)), "1");
+
+        let nearly_two = U256::from(1_999_990_000_000_000_000u64);
+        assert_eq!(format_display(nearly_two, &options(Unit::Eth, Some(3), false, false)), "2.000");
+        assert_eq!(format_display(nearly_two, &options(Unit::Eth, Some(3), false, true)), "2");
+        assert_eq!(format_display(U256::from(10u64).pow(U256::from(15u64)), &options(Unit::Eth, None, false, true)), "0.001");
+        assert_eq!(format_display(U256::MAX, &options(Unit::Eth, Some(2), true, false)).len(), 82);
+    }
+}
@@ -0,0 +1,17 @@
+//! Checkpoint fields for the `bootstrap` command.
+//!
+//! Every phase records its result in a [`Checkpoint`] as soon as it completes,
+//! so an interrupted bootstrap re-run picks up the same wallet and skips work
+//! already done.
+
+use crate::checkpoint::Checkpoint;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Bootstrap {
+    /// Only set when bootstrap generated the key itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet_private_key: Option<String>,
+}
+
+pub type BootstrapState = Checkpoint<Bootstrap>;
@@ -0,0 +1,162 @@
+//! Shareable tab bundles: one tab plus pre-signed guarantees for its planned payments.
+//!
+//! Every guarantee in a bundle is a complete [`Voucher`] for the bundle's tab,
+//! with consecutive `req_id`s, so a marketplace can hand the bundle to a
+//! recipient who checks each seal on its own and draws the payments down
+//! in order.
+
+use crate::error::coded;
+use crate::voucher::Voucher;
+use alloy::primitives::U256;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::str::FromStr;
+
+pub const FORMAT: &str = "4mica-tab-bundle";
+pub const VERSION: u64 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u64,
+    pub tab_id: String,
+    pub user_address: String,
+    pub recipient_address: String,
+    pub ttl: Option<u64>,
+    pub created_at: u64,
+    pub guarantees: Vec<Voucher>,
+}
+
+pub fn write(path: &str, bundle: &Bundle) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(bundle)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write bundle file {}: {}", path, e))
+}
+
+pub fn read(path: &str) -> Result<Bundle> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| coded("INVALID_BUNDLE", format!("Failed to read bundle file {}: {}", path, e)))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| coded("INVALID_BUNDLE", format!("Bundle file {} is not JSON: {}", path, e)))?;
+    parse(value)
+}
+
+fn parse(value: serde_json::Value) -> Result<Bundle> {
+    if value["format"] != FORMAT {
+        return Err(coded("INVALID_BUNDLE", format!("Not a tab bundle: format must be \"{}\"", FORMAT)));
+    }
+    if value["version"] != VERSION {
+        return Err(coded(
+            "UNSUPPORTED_BUNDLE_VERSION",
+            format!("Bundle version {} is not supported; this client reads version {}", value["version"], VERSION),
+        ));
+    }
+    serde_json::from_value(value).map_err(|e| coded("INVALID_BUNDLE", format!("Invalid bundle: {}", e)))
+}
+
+/// Guarantees that do not belong to the bundle's tab and parties, or break the `req_id` sequence.
+/// Seals are checked separately.
+pub fn issues(bundle: &Bundle) -> Vec<String> {
+    let same = |a: &serde_json::Value, b: &str| a.as_str().is_some_and(|a| a.eq_ignore_ascii_case(b));
+    let mut issues = Vec::new();
+    if bundle.guarantees.is_empty() {
+        issues.push("bundle has no guarantees".to_string());
+    }
+    let mut previous_req_id: Option<U256> = None;
+    for (i, guarantee) in bundle.guarantees.iter().enumerate() {
+        let claims = &guarantee.claims;
+        if !same(&claims["tab_id"], &bundle.tab_id) {
+            issues.push(format!("guarantees[{}] is for tab {}, not {}", i, claims["tab_id"], bundle.tab_id));
+        }
+        if !same(&claims["user_address"], &bundle.user_address) {
+            issues.push(format!(
+                "guarantees[{}] is for user {}, not {}",
+                i, claims["user_address"], bundle.user_address
+            ));
+        }
+        if !same(&claims["recipient_address"], &bundle.recipient_address) {
+            issues.push(format!(
+                "guarantees[{}] is for recipient {}, not {}",
+                i, claims["recipient_address"], bundle.recipient_address
+            ));
+        }
+        let req_id = claims["req_id"].as_str().and_then(|req_id| U256::from_str(req_id).ok());
+        match (previous_req_id, req_id) {
+            (_, None) => issues.push(format!("guarantees[{}] has no usable req_id", i)),
+            (Some(previous), Some(req_id)) if previous.checked_add(U256::from(1u64)) != Some(req_id) => {
+                issues.push(format!("guarantees[{}] has req_id {} after {}", i, req_id, previous))
+            }
+            _ => {}
+        }
+        previous_req_id = req_id.or(previous_req_id);
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn guarantee(tab_id: &str, req_id: &str, user: &str) -> serde_json::Value {
+        json!({
+            "format": crate::voucher::FORMAT,
+            "version": crate::voucher::VERSION,
+            "claims": {
+                "tab_id": tab_id,
+                "req_id": req_id,
+                "user_address": user,
+                "recipient_address": "0xBB",
+                "amount": "5"
+            },
+            "signature": "0x",
+            "scheme": "Eip712",
+            "signer": user,
+            "seal": "0x",
+            "created_at": 0
+        })
+    }
+
+    fn bundle(guarantees: Vec<serde_json::Value>) -> serde_json::Value {
+        json!({
+            "format": FORMAT,
+            "version": VERSION,
+            "tab_id": "7",
+            "user_address": "0xAA",
+            "recipient_address": "0xbb",
+            "ttl": null,
+            "created_at": 0,
+            "guarantees": guarantees
+        })
+    }
+
+    #[test]
+    fn consistent_sequences_have_no_issues() {
+        let parsed = parse(bundle(vec![guarantee("7", "1", "0xaa"), guarantee("7", "2", "0xAA")])).unwrap();
+        assert!(issues(&parsed).is_empty());
+    }
+
+    #[test]
+    fn foreign_and_out_of_sequence_guarantees_are_reported() {
+        let parsed = parse(bundle(vec![guarantee("7", "1", "0xaa"), guarantee("8", "3", "0xcc")])).unwrap();
+        assert_eq!(
+            issues(&parsed),
+            [
+                "guarantees[1] is for tab \"8\", not 7",
+                "guarantees[1] is for user \"0xcc\", not 0xAA",
+                "guarantees[1] has req_id 3 after 1",
+            ]
+        );
+        assert_eq!(issues(&parse(bundle(vec![])).unwrap()), ["bundle has no guarantees"]);
+    }
+
+    #[test]
+    fn rejects_other_formats_and_versions() {
+        let err = parse(json!({ "format": crate::voucher::FORMAT, "version": 1 })).unwrap_err();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("INVALID_BUNDLE"));
+        let mut newer = bundle(vec![]);
+        newer["version"] = json!(VERSION + 1);
+        let err = parse(newer).unwrap_err();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("UNSUPPORTED_BUNDLE_VERSION"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Moving unsettled guarantee certificates between hosts.
+//!
+//! An export directory holds one `cert-<tab_id>-<req_id>.json` per certificate
+//! record (claims, signature, certificate, metadata) and a `manifest.json`
+//! listing each file with its SHA-256. The manifest is signed (EIP-191) by the
+//! exporting wallet. Import checks the signature and every file before
+//! writing anything, so a tampered or incomplete export is rejected as a whole
+//! with the first failing file named.
+
+use crate::error::coded;
+use alloy::primitives::{Address, Signature};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const FORMAT: &str = "4mica-cert-export";
+pub const VERSION: u64 = 1;
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file: String,
+    pub sha256: String,
+    pub tab_id: String,
+    pub req_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u64,
+    pub created_at: u64,
+    pub signer: String,
+    pub files: Vec<ManifestEntry>,
+    /// EIP-191 signature over the manifest serialized with this field empty.
+    pub signature: String,
+}
+
+impl Manifest {
+    fn signing_payload(&self) -> Result<String> {
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        Ok(serde_json::to_string(&unsigned)?)
+    }
+}
+
+/// `(tab_id, req_id)` of a certificate record, as decimal strings.
+pub fn record_key(record: &serde_json::Value) -> Result<(String, String)> {
+    let id = |field: &str| -> Result<String> {
+        let value = &record["claims"][field];
+        let text = value.as_str().map(str::to_string).or_else(|| value.as_u64().map(|n| n.to_string()));
+        let text = text.ok_or_else(|| anyhow::anyhow!("Certificate record has no claims.{}", field))?;
+        Ok(alloy::primitives::U256::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid claims.{} '{}': {}", field, text, e))?
+            .to_string())
+    };
+    Ok((id("tab_id")?, id("req_id")?))
+}
+
+fn file_name(tab_id: &str, req_id: &str) -> String {
+    format!("cert-{}-{}.json", tab_id, req_id)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    alloy::hex::encode(Sha256::digest(bytes))
+}
+
+pub fn export(dir: &Path, records: &[serde_json::Value], signer: &PrivateKeySigner, created_at: u64) -> Result<Manifest> {
+    fs::create_dir_all(dir)?;
+    let mut files = Vec::new();
+    for record in records {
+        let (tab_id, req_id) = record_key(record)?;
+        let file = file_name(&tab_id, &req_id);
+        let content = serde_json::to_vec_pretty(record)?;
+        fs::write(dir.join(&file), &content)?;
+        files.push(ManifestEntry {
+            sha256: sha256_hex(&content),
+            file,
+            tab_id,
+            req_id,
+        });
+    }
+
+    let mut manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created_at,
+        signer: signer.address().to_string(),
+        files,
+        signature: String::new(),
+    };
+    let signature = signer.sign_message_sync(manifest.signing_payload()?.as_bytes())?;
+    manifest.signature = alloy::hex::encode_prefixed(signature.as_bytes());
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+/// Checks the manifest signature and every listed file, returning the records in manifest order.
+pub fn verify(dir: &Path, expected_signer: Address) -> Result<(Manifest, Vec<serde_json::Value>)> {
+    let tampered = |file: &str, why: String| coded("CERT_EXPORT_TAMPERED", format!("{}: {}", file, why));
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| coded("CERT_EXPORT_INCOMPLETE", format!("{}: {}", MANIFEST_FILE, e)))?;
+    let manifest: Manifest = serde_json::from_str(&content)
+        .map_err(|e| tampered(MANIFEST_FILE, format!("not a manifest: {}", e)))?;
+    if manifest.format != FORMAT || manifest.version != VERSION {
+        return Err(tampered(MANIFEST_FILE, format!("unsupported format {} v{}", manifest.format, manifest.version)));
+    }
+
+    let recovered = Signature::from_str(&manifest.signature)
+        .map_err(|e| tampered(MANIFEST_FILE, format!("invalid signature: {}", e)))?
+        .recover_address_from_msg(manifest.signing_payload()?.as_bytes())
+        .map_err(|e| tampered(MANIFEST_FILE, format!("signature does not recover: {}", e)))?;
+    if recovered != expected_signer {
+        return Err(tampered(
+            MANIFEST_FILE,
+            format!("signed by {}, expected {}", recovered, expected_signer),
+        ));
+    }
+
+    let mut records = Vec::new();
+    for entry in &manifest.files {
+        if entry.file != file_name(&entry.tab_id, &entry.req_id) {
+            return Err(tampered(&entry.file, "file name does not match its tab_id and req_id".to_string()));
+        }
+        let content = fs::read(dir.join(&entry.file))
+            .map_err(|e| coded("CERT_EXPORT_INCOMPLETE", format!("{}: {}", entry.file, e)))?;
+        if sha256_hex(&content) != entry.sha256 {
+            return Err(tampered(&entry.file, "SHA-256 does not match the manifest".to_string()));
+        }
+        let record: serde_json::Value = serde_json::from_slice(&content)
+            .map_err(|e| tampered(&entry.file, format!("not JSON: {}", e)))?;
+        if record_key(&record)? != (entry.tab_id.clone(), entry.req_id.clone()) {
+            return Err(tampered(&entry.file, "claims do not match the manifest entry".to_string()));
+        }
+        records.push(record);
+    }
+    Ok((manifest, records))
+}
+
+/// Writes records into `store`, skipping `(tab_id, req_id)` pairs already there.
+pub fn import(store: &Path, records: &[serde_json::Value]) -> Result<(Vec<String>, Vec<String>)> {
+    fs::create_dir_all(store)?;
+    let (mut imported, mut skipped) = (Vec::new(), Vec::new());
+    for record in records {
+        let (tab_id, req_id) = record_key(record)?;
+        let file = file_name(&tab_id, &req_id);
+        let path = store.join(&file);
+        if path.exists() {
+            skipped.push(file);
+            continue;
+        }
+        fs::write(path, serde_json::to_vec_pretty(record)?)?;
+        imported.push(file);
+    }
+    Ok((imported, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::error_code;
+
+    fn record(tab_id: &str, req_id: u64) -> serde_json::Value {
+        serde_json::json!({
+            "claims": { "tab_id": tab_id, "req_id": req_id.to_string(), "amount": "1000" },
+            "signature": "0x01",
+            "certificate": "cert-bytes"
+        })
+    }
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("certstore-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn exports_verify_and_import_once() {
+        let signer = PrivateKeySigner::random();
+        let (export_dir, store) = (scratch("export"), scratch("store"));
+        export(&export_dir, &[record("7", 1), record("7", 2)], &signer, 1_700_000_000).unwrap();
+
+        let (manifest, records) = verify(&export_dir, signer.address()).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(import(&store, &records).unwrap().0.len(), 2);
+        let (imported, skipped) = import(&store, &records).unwrap();
+        assert!(imported.is_empty());
+        assert_eq!(skipped, ["cert-7-1.json", "cert-7-2.json"]);
+
+        let err = verify(&export_dir, PrivateKeySigner::random().address()).unwrap_err();
+        assert_eq!(error_code(&err).as_deref(), Some("CERT_EXPORT_TAMPERED"));
+
+        fs::remove_dir_all(export_dir).unwrap();
+        fs::remove_dir_all(store).unwrap();
+    }
+
+    #[test]
+    fn tampered_or_missing_files_are_named() {
+        let signer = PrivateKeySigner::random();
+        let dir = scratch("tamper");
+        export(&dir, &[record("7", 1), record("7", 2)], &signer, 1_700_000_000).unwrap();
+
+        fs::write(dir.join("cert-7-2.json"), serde_json::to_vec_pretty(&record("7", 2).to_string()).unwrap()).unwrap();
+        let err = verify(&dir, signer.address()).unwrap_err();
+        assert_eq!(error_code(&err).as_deref(), Some("CERT_EXPORT_TAMPERED"));
+        assert!(err.to_string().starts_with("cert-7-2.json"));
+
+        fs::remove_file(dir.join("cert-7-1.json")).unwrap();
+        let err = verify(&dir, signer.address()).unwrap_err();
+        assert_eq!(error_code(&err).as_deref(), Some("CERT_EXPORT_INCOMPLETE"));
+        assert!(err.to_string().starts_with("cert-7-1.json"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
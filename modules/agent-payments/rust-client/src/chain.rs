@@ -0,0 +1,157 @@
+//! Direct chain access for data the SDK does not expose.
+//!
+//! No core contract ABI is bundled with this client, so nothing here calls
+//! the 4Mica contract's own functions or decodes its events. At the contract
+//! address only standard interfaces are read: EIP-5267 `eip712Domain()` and
+//! the EIP-1967/EIP-1822 proxy slots.
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::network::EthereumWallet;
+use alloy::primitives::{b256, Address, B256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionReceipt;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use anyhow::Result;
+use std::str::FromStr;
+
+sol! {
+    #[sol(rpc)]
+    interface IERC5267 {
+        function eip712Domain() external view returns (bytes1 fields, string name, string version, uint256 chainId, address verifyingContract, bytes32 salt, uint256[] extensions);
+    }
+}
+
+/// EIP-1967 `bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`.
+const EIP1967_IMPLEMENTATION_SLOT: B256 = b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+/// EIP-1967 `bytes32(uint256(keccak256("eip1967.proxy.admin")) - 1)`.
+const EIP1967_ADMIN_SLOT: B256 = b256!("b53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103");
+/// EIP-1822 `keccak256("PROXIABLE")`.
+const EIP1822_PROXIABLE_SLOT: B256 = b256!("c5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+
+/// Proxy pattern found at the contract address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyStatus {
+    pub pattern: &'static str,
+    pub implementation: Address,
+    /// Only EIP-1967 proxies record an admin.
+    pub admin: Option<Address>,
+}
+
+pub struct Chain {
+    pub provider: DynProvider,
+    /// Address of the 4Mica core contract the SDK is configured with.
+    pub contract_address: Address,
+    pub wallet_address: Address,
+}
+
+impl Chain {
+    pub fn new(rpc_url: &str, contract_address: &str, private_key: &str) -> Result<Self> {
+        let signer = signer_from_key(private_key)?;
+        let wallet_address = signer.address();
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(signer))
+            .connect_client(crate::wirelog::rpc_client(rpc_url)?)
+            .erased();
+        let contract_address = Address::from_str(contract_address)?;
+
+        Ok(Chain {
+            provider,
+            contract_address,
+            wallet_address,
+        })
+    }
+
+    /// The core contract's EIP-5267 interface; deployments without it revert.
+    pub fn eip5267(&self) -> IERC5267::IERC5267Instance<DynProvider> {
+        IERC5267::new(self.contract_address, self.provider.clone())
+    }
+
+    pub async fn block_timestamp(&self, block: BlockNumberOrTag) -> Result<u64> {
+        let block = self
+            .provider
+            .get_block_by_number(block)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Block {} not found", block))?;
+        Ok(block.header.timestamp)
+    }
+
+    /// Mean seconds per block over the last `span` blocks, or `None` on a chain too short to tell.
+    pub async fn mean_block_time(&self, span: u64) -> Result<Option<f64>> {
+        let head = self.provider.get_block_number().await?;
+        let span = span.min(head);
+        if span == 0 {
+            return Ok(None);
+        }
+        let newest = self.block_timestamp(BlockNumberOrTag::Number(head)).await?;
+        let oldest = self.block_timestamp(BlockNumberOrTag::Number(head - span)).await?;
+        Ok((newest > oldest).then(|| (newest - oldest) as f64 / span as f64))
+    }
+
+    /// First block mined at or after `timestamp`; one past the head if none is yet.
+    pub async fn first_block_at_or_after(&self, timestamp: u64) -> Result<u64> {
+        let (mut low, mut high) = (0u64, self.provider.get_block_number().await?);
+        if self.block_timestamp(BlockNumberOrTag::Number(high)).await? < timestamp {
+            return Ok(high + 1);
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.block_timestamp(BlockNumberOrTag::Number(mid)).await? < timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+
+    /// The contract's proxy pattern and implementation, or `None` when neither proxy slot is set.
+    pub async fn proxy_status(&self) -> Result<Option<ProxyStatus>> {
+        let address = self.contract_address;
+        let slot_address = |slot: B256| async move {
+            let word = self.provider.get_storage_at(address, slot.into()).await?;
+            Ok::<_, anyhow::Error>(Address::from_word(word.into()))
+        };
+
+        let implementation = slot_address(EIP1967_IMPLEMENTATION_SLOT).await?;
+        if !implementation.is_zero() {
+            let admin = slot_address(EIP1967_ADMIN_SLOT).await?;
+            return Ok(Some(ProxyStatus {
+                pattern: "eip1967",
+                implementation,
+                admin: (!admin.is_zero()).then_some(admin),
+            }));
+        }
+        let implementation = slot_address(EIP1822_PROXIABLE_SLOT).await?;
+        Ok((!implementation.is_zero()).then_some(ProxyStatus {
+            pattern: "eip1822",
+            implementation,
+            admin: None,
+        }))
+    }
+}
+
+pub fn signer_from_key(private_key: &str) -> Result<PrivateKeySigner> {
+    PrivateKeySigner::from_str(private_key).map_err(|e| anyhow::anyhow!("Invalid wallet private key: {}", e))
+}
+
+pub fn receipt_json(receipt: &TransactionReceipt) -> serde_json::Value {
+    serde_json::json!({
+        "transaction_hash": receipt.transaction_hash,
+        "block_number": receipt.block_number,
+        "gas_used": receipt.gas_used
+    })
+}
+
+/// Fails with the transaction hash when the receipt reports a revert.
+pub fn ensure_success(receipt: TransactionReceipt, what: &str) -> Result<TransactionReceipt> {
+    if receipt.status() {
+        Ok(receipt)
+    } else {
+        Err(anyhow::anyhow!("{} reverted in transaction {}", what, receipt.transaction_hash))
+    }
+}
+
+pub fn parse_address(value: &str, field: &str) -> Result<Address> {
+    Address::from_str(value).map_err(|e| anyhow::anyhow!("Invalid {} '{}': {}", field, value, e))
+}
@@ -0,0 +1,93 @@
+//! Resumable phase checkpoints for multi-step commands.
+//!
+//! `bootstrap` and `transfer_collateral_to_new_agent` send several
+//! transactions that cannot be undone together, so instead of pretending to
+//! be atomic they record each phase's result as soon as it completes. A
+//! re-run with the same state file skips finished phases. The file is
+//! replaced through a rename, so an interrupted write leaves the previous
+//! checkpoint intact, and is only readable by its owner because bootstrap
+//! may store a generated private key in it.
+
+use crate::console::estatus;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+
+/// Command-specific fields in `data`, stored alongside `phases` in one JSON object.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint<T> {
+    #[serde(flatten)]
+    pub data: T,
+    pub phases: BTreeMap<String, serde_json::Value>,
+    #[serde(skip)]
+    path: String,
+    #[serde(skip)]
+    label: &'static str,
+}
+
+impl<T: Default + Serialize + DeserializeOwned> Checkpoint<T> {
+    /// Loads `path`, or starts empty when it does not exist yet. `label` names the command in messages.
+    pub fn load(path: &str, label: &'static str) -> Result<Self> {
+        let mut checkpoint: Checkpoint<T> = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Corrupt {} state {}: {}", label, path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Checkpoint::default(),
+            Err(e) => return Err(anyhow::anyhow!("Failed to read {} state {}: {}", label, path, e)),
+        };
+        checkpoint.path = path.to_string();
+        checkpoint.label = label;
+        Ok(checkpoint)
+    }
+
+    pub fn is_complete(&self, phase: &str) -> bool {
+        self.phases.contains_key(phase)
+    }
+
+    pub fn complete(&mut self, phase: &str, result: serde_json::Value) -> Result<()> {
+        estatus!("✅ {} phase {} complete", self.label, phase);
+        self.phases.insert(phase.to_string(), result);
+        self.save()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let tmp = format!("{}.tmp", self.path);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&tmp)?.write_all(content.as_bytes())?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Fields {
+        target: Option<String>,
+    }
+
+    #[test]
+    fn a_reloaded_checkpoint_keeps_its_fields_and_finished_phases() {
+        let path = std::env::temp_dir().join(format!("checkpoint-test-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut checkpoint = Checkpoint::<Fields>::load(path, "Test").unwrap();
+        assert!(checkpoint.phases.is_empty());
+        checkpoint.data.target = Some("0xb0".to_string());
+        checkpoint.complete("first", serde_json::json!({ "ok": true })).unwrap();
+
+        let on_disk: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(on_disk, serde_json::json!({ "target": "0xb0", "phases": { "first": { "ok": true } } }));
+        let reloaded = Checkpoint::<Fields>::load(path, "Test").unwrap();
+        assert_eq!(reloaded.data.target.as_deref(), Some("0xb0"));
+        assert!(reloaded.is_complete("first") && !reloaded.is_complete("second"));
+        fs::remove_file(path).unwrap();
+    }
+}
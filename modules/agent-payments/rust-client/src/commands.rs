@@ -0,0 +1,148 @@
+//! Classification of every command by the state it can change.
+//!
+//! This table is the single source for "does this command write?" questions:
+//! unknown-command detection, `read_only` enforcement and the funding floor
+//! all consult it instead of keeping their own lists.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandKind {
+    /// Broadcasts a transaction and spends wallet gas.
+    ChainWrite,
+    /// Changes off-chain state only (4Mica API records or local files).
+    LocalWrite,
+    /// Pure read; safe to repeat or run in parallel.
+    Read,
+}
+
+pub const COMMANDS: &[(&str, CommandKind)] = &[
+    ("test_connection", CommandKind::Read),
+    ("deposit", CommandKind::ChainWrite),
+    ("get_user", CommandKind::Read),
+    ("create_tab", CommandKind::LocalWrite),
+    ("sign_payment", CommandKind::Read),
+    ("sign_multiple_req_ids", CommandKind::Read),
+    ("issue_payment_guarantee", CommandKind::LocalWrite),
+    ("pay_tab", CommandKind::ChainWrite),
+    ("get_tab_payment_status", CommandKind::Read),
+    ("remunerate", CommandKind::ChainWrite),
+    ("verify_bls_signature", CommandKind::Read),
+    ("verify_certificate_quorum", CommandKind::Read),
+    ("compare_outputs", CommandKind::Read),
+    ("batch_create_tabs_and_sign", CommandKind::LocalWrite),
+    ("get_funding_status", CommandKind::Read),
+    ("sign_typed_data", CommandKind::Read),
+    ("verify_typed_data", CommandKind::Read),
+    ("sign_payment_for_operator", CommandKind::Read),
+    ("bootstrap", CommandKind::ChainWrite),
+    ("verify_eip191_signature", CommandKind::Read),
+    ("suggest_settlement_window", CommandKind::Read),
+    ("verify_webhook_signature", CommandKind::Read),
+    ("get_tab_settlement_status", CommandKind::Read),
+    ("create_voucher", CommandKind::LocalWrite),
+    ("verify_payment_signature", CommandKind::Read),
+    ("compute_required_collateral", CommandKind::Read),
+    ("export_wallet", CommandKind::Read),
+    ("format_amount", CommandKind::Read),
+    ("convert_amount", CommandKind::Read),
+    ("list_environments", CommandKind::Read),
+    ("verify_work_binding", CommandKind::Read),
+    ("transfer_collateral_to_new_agent", CommandKind::ChainWrite),
+    ("sign_payment_ecdsa_raw", CommandKind::Read),
+    ("simulate_flow", CommandKind::ChainWrite),
+    ("get_contract_upgrade_status", CommandKind::Read),
+    ("benchmark_rpc", CommandKind::Read),
+    ("get_estimated_confirmation_time", CommandKind::Read),
+    ("compat_report", CommandKind::Read),
+    ("verify_build", CommandKind::Read),
+    ("get_eip712_domain_separator", CommandKind::Read),
+    ("create_signed_tab_bundle", CommandKind::LocalWrite),
+    ("load_signed_tab_bundle", CommandKind::Read),
+    ("get_gas_savings_estimate", CommandKind::Read),
+    ("redact_output", CommandKind::LocalWrite),
+    ("export_certs", CommandKind::LocalWrite),
+    ("import_certs", CommandKind::LocalWrite),
+];
+
+/// Commands that draw on collateral. With `config.auto_topup` set (and not
+/// `read_only`) they may send a `deposit` before running, so they can spend
+/// gas even though their own kind is not [`CommandKind::ChainWrite`]. That
+/// top-up is held to the funding floor and receipt verification on its own.
+/// pay_tab is excluded because it pays from the wallet balance a top-up would drain.
+pub const COLLATERAL_COMMANDS: &[&str] = &[
+    "sign_payment",
+    "sign_payment_ecdsa_raw",
+    "sign_payment_for_operator",
+    "issue_payment_guarantee",
+    "batch_create_tabs_and_sign",
+];
+
+pub fn command_kind(command: &str) -> Option<CommandKind> {
+    COMMANDS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, kind)| *kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn commands_of(kind: CommandKind) -> Vec<&'static str> {
+        COMMANDS
+            .iter()
+            .filter(|(_, k)| *k == kind)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    #[test]
+    fn every_command_is_classified_once() {
+        let mut seen = HashSet::new();
+        for (name, _) in COMMANDS {
+            assert!(seen.insert(*name), "{} is classified twice", name);
+        }
+    }
+
+    // Changing either list below is a behavior change for read_only mode and
+    // the funding floor, so it must be a deliberate edit reviewed with the handler.
+    #[test]
+    fn chain_writes_match_allowlist() {
+        assert_eq!(
+            commands_of(CommandKind::ChainWrite),
+            [
+                "deposit",
+                "pay_tab",
+                "remunerate",
+                "bootstrap",
+                "transfer_collateral_to_new_agent",
+                "simulate_flow",
+            ]
+        );
+    }
+
+    #[test]
+    fn collateral_commands_are_not_chain_writes() {
+        for command in COLLATERAL_COMMANDS {
+            let kind = command_kind(command);
+            assert!(kind.is_some() && kind != Some(CommandKind::ChainWrite), "{}", command);
+        }
+    }
+
+    #[test]
+    fn local_writes_match_allowlist() {
+        assert_eq!(
+            commands_of(CommandKind::LocalWrite),
+            [
+                "create_tab",
+                "issue_payment_guarantee",
+                "batch_create_tabs_and_sign",
+                "create_voucher",
+                "create_signed_tab_bundle",
+                "redact_output",
+                "export_certs",
+                "import_certs",
+            ]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Field-by-field comparison of Output files for upgrade validation.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Fields that legitimately differ between runs of the same input.
+pub const DEFAULT_VOLATILE_FIELDS: &[&str] = &[
+    "timestamp",
+    "transaction_hash",
+    "block_number",
+    "block_hash",
+    "gas_used",
+    "gas_price",
+    "effective_gas_price",
+    "duration_ms",
+];
+
+#[derive(Debug, Serialize)]
+pub struct Difference {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    pointer: String,
+    kind: &'static str,
+    left: Option<Value>,
+    right: Option<Value>,
+}
+
+struct Comparison<'a> {
+    ignored: &'a [String],
+    file: Option<String>,
+    differences: Vec<Difference>,
+}
+
+/// Compares two Output files, or two directories of them paired by file name.
+pub fn compare_paths(left: &Path, right: &Path, ignored: &[String]) -> Result<Vec<Difference>> {
+    let mut comparison = Comparison {
+        ignored,
+        file: None,
+        differences: Vec::new(),
+    };
+
+    match (left.is_dir(), right.is_dir()) {
+        (true, true) => {
+            let left_files = json_file_names(left)?;
+            let right_files = json_file_names(right)?;
+            for name in left_files.union(&right_files) {
+                comparison.file = Some(name.clone());
+                match (left_files.contains(name), right_files.contains(name)) {
+                    (true, true) => {
+                        let l = read_json(&left.join(name))?;
+                        let r = read_json(&right.join(name))?;
+                        comparison.compare(&l, &r, "", None);
+                    }
+                    (true, false) => comparison.record("", None, "missing_right", None, None),
+                    _ => comparison.record("", None, "missing_left", None, None),
+                }
+            }
+        }
+        (false, false) => {
+            let l = read_json(left)?;
+            let r = read_json(right)?;
+            comparison.compare(&l, &r, "", None);
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Cannot compare a file with a directory: {} vs {}",
+                left.display(),
+                right.display()
+            ))
+        }
+    }
+
+    Ok(comparison.differences)
+}
+
+impl Comparison<'_> {
+    fn compare(&mut self, left: &Value, right: &Value, pointer: &str, request_id: Option<&str>) {
+        match (left, right) {
+            (Value::Object(l), Value::Object(r)) => {
+                let keys: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+                for key in keys {
+                    let child = format!("{}/{}", pointer, escape_pointer_token(key));
+                    if self.is_ignored(key, &child) {
+                        continue;
+                    }
+                    match (l.get(key), r.get(key)) {
+                        (Some(lv), Some(rv)) => self.compare(lv, rv, &child, request_id),
+                        (Some(lv), None) => self.record(&child, request_id, "missing_right", Some(lv), None),
+                        (None, Some(rv)) => self.record(&child, request_id, "missing_left", None, Some(rv)),
+                        (None, None) => {}
+                    }
+                }
+            }
+            (Value::Array(l), Value::Array(r)) => match (by_request_id(l), by_request_id(r)) {
+                // Batch outputs are matched item-by-item on request_id rather than position
+                (Some(l_items), Some(r_items)) => {
+                    let ids: BTreeSet<&String> = l_items.keys().chain(r_items.keys()).collect();
+                    for id in ids {
+                        match (l_items.get(id), r_items.get(id)) {
+                            (Some((i, lv)), Some((_, rv))) => {
+                                self.compare(lv, rv, &format!("{}/{}", pointer, i), Some(id))
+                            }
+                            (Some((i, lv)), None) => self.record(
+                                &format!("{}/{}", pointer, i),
+                                Some(id),
+                                "missing_right",
+                                Some(lv),
+                                None,
+                            ),
+                            (None, Some((i, rv))) => self.record(
+                                &format!("{}/{}", pointer, i),
+                                Some(id),
+                                "missing_left",
+                                None,
+                                Some(rv),
+                            ),
+                            (None, None) => {}
+                        }
+                    }
+                }
+                _ => {
+                    for i in 0..l.len().max(r.len()) {
+                        let child = format!("{}/{}", pointer, i);
+                        match (l.get(i), r.get(i)) {
+                            (Some(lv), Some(rv)) => self.compare(lv, rv, &child, request_id),
+                            (Some(lv), None) => self.record(&child, request_id, "missing_right", Some(lv), None),
+                            (None, Some(rv)) => self.record(&child, request_id, "missing_left", None, Some(rv)),
+                            (None, None) => {}
+                        }
+                    }
+                }
+            },
+            _ => {
+                if left != right {
+                    self.record(pointer, request_id, "changed", Some(left), Some(right));
+                }
+            }
+        }
+    }
+
+    fn is_ignored(&self, key: &str, pointer: &str) -> bool {
+        self.ignored.iter().any(|field| field == key || field == pointer)
+    }
+
+    fn record(
+        &mut self,
+        pointer: &str,
+        request_id: Option<&str>,
+        kind: &'static str,
+        left: Option<&Value>,
+        right: Option<&Value>,
+    ) {
+        self.differences.push(Difference {
+            file: self.file.clone(),
+            request_id: request_id.map(str::to_string),
+            pointer: pointer.to_string(),
+            kind,
+            left: left.cloned(),
+            right: right.cloned(),
+        });
+    }
+}
+
+/// Indexes array items by `request_id` when every item carries a unique one.
+fn by_request_id(items: &[Value]) -> Option<BTreeMap<String, (usize, &Value)>> {
+    if items.is_empty() {
+        return None;
+    }
+    let mut keyed = BTreeMap::new();
+    for (i, item) in items.iter().enumerate() {
+        let id = match &item["request_id"] {
+            Value::String(id) => id.clone(),
+            Value::Number(id) => id.to_string(),
+            _ => return None,
+        };
+        if keyed.insert(id, (i, item)).is_some() {
+            return None;
+        }
+    }
+    Some(keyed)
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn json_file_names(dir: &Path) -> Result<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid JSON in {}: {}", path.display(), e))
+}
@@ -0,0 +1,252 @@
+//! Behavior-affecting changes by version, for `compat_report`.
+//!
+//! Orchestrators diff the running version against the one they last
+//! validated and gate upgrades on the result, so every change to the command
+//! registry or to emitted error codes needs an entry here; the tests below
+//! fail when one is missing. Output fields and default changes are recorded
+//! by hand.
+
+use crate::error::coded;
+use anyhow::Result;
+
+/// Version whose commands predate this table.
+pub const BASELINE_VERSION: &str = "0.1.0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    CommandAdded,
+    CommandChanged,
+    OutputFieldAdded,
+    ErrorCodeAdded,
+    DefaultChanged,
+}
+
+impl ChangeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::CommandAdded => "command_added",
+            ChangeKind::CommandChanged => "command_changed",
+            ChangeKind::OutputFieldAdded => "output_field_added",
+            ChangeKind::ErrorCodeAdded => "error_code_added",
+            ChangeKind::DefaultChanged => "default_changed",
+        }
+    }
+}
+
+pub struct Change {
+    pub version: &'static str,
+    pub kind: ChangeKind,
+    pub subject: &'static str,
+    pub detail: &'static str,
+}
+
+const fn change(version: &'static str, kind: ChangeKind, subject: &'static str, detail: &'static str) -> Change {
+    Change {
+        version,
+        kind,
+        subject,
+        detail,
+    }
+}
+
+use ChangeKind::*;
+
+pub const CHANGES: &[Change] = &[
+    change("0.2.0", CommandAdded, "sign_multiple_req_ids", ""),
+    change("0.2.0", CommandAdded, "verify_certificate_quorum", ""),
+    change("0.2.0", CommandAdded, "compare_outputs", ""),
+    change("0.2.0", CommandAdded, "batch_create_tabs_and_sign", ""),
+    change("0.2.0", CommandAdded, "get_funding_status", ""),
+    change("0.2.0", CommandAdded, "sign_typed_data", ""),
+    change("0.2.0", CommandAdded, "verify_typed_data", ""),
+    change("0.2.0", CommandAdded, "sign_payment_for_operator", ""),
+    change("0.2.0", CommandAdded, "bootstrap", ""),
+    change("0.2.0", CommandAdded, "verify_eip191_signature", ""),
+    change("0.2.0", CommandAdded, "suggest_settlement_window", ""),
+    change("0.2.0", CommandAdded, "verify_webhook_signature", ""),
+    change("0.2.0", CommandAdded, "get_tab_settlement_status", ""),
+    change("0.2.0", CommandAdded, "create_voucher", ""),
+    change("0.2.0", CommandAdded, "verify_payment_signature", ""),
+    change("0.2.0", CommandAdded, "compute_required_collateral", ""),
+    change("0.2.0", CommandAdded, "export_wallet", ""),
+    change("0.2.0", CommandAdded, "format_amount", ""),
+    change("0.2.0", CommandAdded, "convert_amount", ""),
+    change("0.2.0", CommandAdded, "list_environments", ""),
+    change("0.2.0", CommandAdded, "verify_work_binding", ""),
+    change("0.2.0", CommandAdded, "transfer_collateral_to_new_agent", ""),
+    change("0.2.0", CommandAdded, "sign_payment_ecdsa_raw", ""),
+    change("0.2.0", CommandAdded, "simulate_flow", ""),
+    change("0.2.0", CommandAdded, "get_contract_upgrade_status", ""),
+    change("0.2.0", CommandAdded, "benchmark_rpc", ""),
+    change("0.2.0", CommandAdded, "get_estimated_confirmation_time", ""),
+    change("0.2.0", CommandAdded, "export_certs", ""),
+    change("0.2.0", CommandAdded, "import_certs", ""),
+    change("0.2.0", CommandAdded, "compat_report", ""),
+    change("0.2.0", CommandAdded, "verify_build", ""),
+    change("0.2.0", CommandAdded, "get_eip712_domain_separator", ""),
+    change("0.2.0", CommandAdded, "create_signed_tab_bundle", ""),
+    change("0.2.0", CommandAdded, "load_signed_tab_bundle", ""),
+    change("0.2.0", CommandAdded, "get_gas_savings_estimate", ""),
+    change("0.2.0", CommandAdded, "redact_output", ""),
+    change("0.2.0", ErrorCodeAdded, "ALREADY_REMUNERATED", ""),
+    change("0.2.0", ErrorCodeAdded, "BOOTSTRAP_FUNDING_TIMEOUT", ""),
+    change("0.2.0", ErrorCodeAdded, "BUILD_NOT_VERIFIED", ""),
+    change("0.2.0", ErrorCodeAdded, "CERT_EXPORT_INCOMPLETE", ""),
+    change("0.2.0", ErrorCodeAdded, "CERT_EXPORT_TAMPERED", ""),
+    change("0.2.0", ErrorCodeAdded, "CREATE_TAB_FAILED", ""),
+    change("0.2.0", ErrorCodeAdded, "DOMAIN_UNKNOWN", ""),
+    change("0.2.0", ErrorCodeAdded, "ENS_NOT_SUPPORTED", ""),
+    change("0.2.0", ErrorCodeAdded, "ENS_RESOLUTION_FAILED", ""),
+    change("0.2.0", ErrorCodeAdded, "ENVIRONMENTS_FILE_UNREADABLE", ""),
+    change("0.2.0", ErrorCodeAdded, "ENVIRONMENT_NOT_FOUND", ""),
+    change("0.2.0", ErrorCodeAdded, "FEE_BELOW_RECENT_RANGE", ""),
+    change("0.2.0", ErrorCodeAdded, "INPUT_TOO_DEEP", ""),
+    change("0.2.0", ErrorCodeAdded, "INPUT_TOO_LARGE", ""),
+    change("0.2.0", ErrorCodeAdded, "INTERNAL_ERROR", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_AMOUNT", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_BUNDLE", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_CONVERSION", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_DELEGATION", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_INPUT", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_VERSION", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_VOUCHER", ""),
+    change("0.2.0", ErrorCodeAdded, "INVALID_WORK_HASH", ""),
+    change("0.2.0", ErrorCodeAdded, "NEW_AGENT_KEY_MISMATCH", ""),
+    change("0.2.0", ErrorCodeAdded, "NEW_AGENT_UNFUNDED", ""),
+    change("0.2.0", ErrorCodeAdded, "NO_COLLATERAL", ""),
+    change("0.2.0", ErrorCodeAdded, "PAUSED_LOW_FUNDS", ""),
+    change("0.2.0", ErrorCodeAdded, "READ_ONLY", ""),
+    change("0.2.0", ErrorCodeAdded, "REDACTION_PROFILE_NOT_FOUND", ""),
+    change("0.2.0", ErrorCodeAdded, "SIGNER_NOT_USER", ""),
+    change("0.2.0", ErrorCodeAdded, "SIGN_PAYMENT_FAILED", ""),
+    change("0.2.0", ErrorCodeAdded, "SIMULATION_REFUSED", ""),
+    change("0.2.0", ErrorCodeAdded, "UNSAFE_LOGGING_REFUSED", ""),
+    change("0.2.0", ErrorCodeAdded, "UNSUPPORTED_BUNDLE_VERSION", ""),
+    change("0.2.0", ErrorCodeAdded, "UNSUPPORTED_VOUCHER_VERSION", ""),
+    change("0.2.0", ErrorCodeAdded, "VERIFICATION_MISMATCH", ""),
+    change("0.2.0", ErrorCodeAdded, "VERIFICATION_UNAVAILABLE", ""),
+    change("0.2.0", ErrorCodeAdded, "VOUCHER_TAMPERED", ""),
+    change("0.2.0", ErrorCodeAdded, "WITHDRAWAL_DELAY_PENDING", ""),
+    change("0.2.0", OutputFieldAdded, "error_code", "Stable code on failures, alongside the error message"),
+    change("0.2.0", OutputFieldAdded, "partial", "Set with steps and resume when a multi-step command fails partway"),
+    change("0.2.0", OutputFieldAdded, "environment", "Name of the environment selected with --env"),
+    change("0.2.0", OutputFieldAdded, "ens_resolutions", "ENS names resolved in the arguments, when any were"),
+    change("0.2.0", CommandChanged, "sign_payment", "work_hash or work_artifact_file adds a work_binding to the output"),
+    change("0.2.0", CommandChanged, "issue_payment_guarantee", "Accepts voucher or voucher_file in place of claims and signature"),
+    change(
+        "0.2.0",
+        CommandChanged,
+        "remunerate",
+        "Remunerates the tab named by claims/signature or voucher instead of paying a fixed tab and amount",
+    ),
+    change("0.2.0", DefaultChanged, "amounts", "Malformed amount arguments fail with INVALID_AMOUNT instead of being read loosely"),
+    change(
+        "0.2.0",
+        DefaultChanged,
+        "ids",
+        "Missing or malformed tab_id and req_id arguments fail with INVALID_INPUT instead of defaulting to 0",
+    ),
+];
+
+pub fn parse_version(text: &str) -> Result<(u64, u64, u64)> {
+    let invalid = || coded("INVALID_VERSION", format!("'{}' is not a MAJOR.MINOR.PATCH version", text));
+    let mut parts = text.trim_start_matches('v').split('.').map(|part| part.parse::<u64>().map_err(|_| invalid()));
+    let version = (
+        parts.next().ok_or_else(invalid)??,
+        parts.next().ok_or_else(invalid)??,
+        parts.next().ok_or_else(invalid)??,
+    );
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(version)
+}
+
+/// Changes after `since`, up to and including `running`, oldest first.
+pub fn changes_since(since: &str, running: &str) -> Result<Vec<&'static Change>> {
+    let (since, running) = (parse_version(since)?, parse_version(running)?);
+    let mut changes = Vec::new();
+    for change in CHANGES {
+        let version = parse_version(change.version)?;
+        if version > since && version <= running {
+            changes.push(change);
+        }
+    }
+    changes.sort_by_key(|change| parse_version(change.version).ok());
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Commands of [`BASELINE_VERSION`].
+    const BASELINE_COMMANDS: &[&str] = &[
+        "test_connection",
+        "deposit",
+        "get_user",
+        "create_tab",
+        "sign_payment",
+        "issue_payment_guarantee",
+        "pay_tab",
+        "get_tab_payment_status",
+        "remunerate",
+        "verify_bls_signature",
+    ];
+
+    fn recorded(kind: ChangeKind) -> HashSet<&'static str> {
+        CHANGES.iter().filter(|change| change.kind == kind).map(|change| change.subject).collect()
+    }
+
+    #[test]
+    fn every_registered_command_is_recorded() {
+        let added = recorded(CommandAdded);
+        for (name, _) in crate::commands::COMMANDS {
+            assert!(
+                BASELINE_COMMANDS.contains(name) || added.contains(name),
+                "{} is registered without a command_added entry",
+                name
+            );
+        }
+        for name in added {
+            assert!(crate::commands::command_kind(name).is_some(), "{} is recorded as added but is not registered", name);
+        }
+    }
+
+    #[test]
+    fn every_emitted_error_code_is_recorded() {
+        let added = recorded(ErrorCodeAdded);
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let code = source.split("#[cfg(test)]").next().unwrap_or("");
+            for call in code.split("coded(").skip(1) {
+                let Some(literal) = call.trim_start().strip_prefix('"') else { continue };
+                let name = literal.split('"').next().unwrap_or("");
+                if name.chars().all(|c| c.is_ascii_uppercase() || c == '_') && !name.is_empty() {
+                    assert!(added.contains(name), "{} emits {} without an error_code_added entry", path.display(), name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn versions_are_released_or_current() {
+        let running = parse_version(env!("CARGO_PKG_VERSION")).unwrap();
+        for change in CHANGES {
+            let version = parse_version(change.version).unwrap();
+            assert!(version > parse_version(BASELINE_VERSION).unwrap() && version <= running, "{}", change.version);
+        }
+    }
+
+    #[test]
+    fn reports_only_changes_after_since() {
+        assert_eq!(changes_since("0.1.0", "0.2.0").unwrap().len(), CHANGES.len());
+        assert!(changes_since("0.2.0", "0.2.0").unwrap().is_empty());
+        assert!(changes_since("v0.1", "0.2.0").is_err());
+        assert!(parse_version("1.2.3.4").is_err());
+        assert_eq!(parse_version("v1.2.3").unwrap(), (1, 2, 3));
+    }
+}
@@ -0,0 +1,73 @@
+//! Human-readable progress lines on stdout/stderr.
+//!
+//! With `config.log_ascii` set, every line is reduced to printable ASCII so
+//! log parsers that choke on emoji or other multi-byte text can consume it.
+//! The status emoji map to bracketed tags; any other non-ASCII character and
+//! control characters other than tab are dropped.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+
+const TAGS: &[(char, &str)] = &[
+    ('✅', "[ok]"),
+    ('❌', "[fail]"),
+    ('⚠', "[warn]"),
+    ('🔍', "[check]"),
+    ('⏳', "[wait]"),
+    ('💰', "[deposit]"),
+];
+
+pub fn set_ascii_only(enabled: bool) {
+    ASCII_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+pub fn render(line: &str) -> String {
+    if ASCII_ONLY.load(Ordering::Relaxed) {
+        to_ascii(line)
+    } else {
+        line.to_string()
+    }
+}
+
+pub fn to_ascii(line: &str) -> String {
+    let mut ascii = String::with_capacity(line.len());
+    for c in line.chars() {
+        if let Some((_, tag)) = TAGS.iter().find(|(emoji, _)| *emoji == c) {
+            ascii.push_str(tag);
+        } else if c == '\t' || (c.is_ascii() && !c.is_ascii_control()) {
+            ascii.push(c);
+        }
+    }
+    ascii
+}
+
+/// `println!` that honours `log_ascii`.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        println!("{}", $crate::console::render(&format!($($arg)*)))
+    };
+}
+
+/// `eprintln!` that honours `log_ascii`.
+macro_rules! estatus {
+    ($($arg:tt)*) => {
+        eprintln!("{}", $crate::console::render(&format!($($arg)*)))
+    };
+}
+
+pub(crate) use {estatus, status};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_mode_tags_status_emoji_and_strips_the_rest() {
+        assert_eq!(to_ascii("✅ Bootstrap phase wallet complete"), "[ok] Bootstrap phase wallet complete");
+        // U+26A0 is followed by a variation selector, which is dropped
+        assert_eq!(to_ascii("⚠️  low balance"), "[warn]  low balance");
+        assert_eq!(to_ascii("memo: 🚀 שלום café"), "memo:   caf");
+        assert_eq!(to_ascii("a\u{0}b\u{1b}[31mc\td\r\n"), "ab[31mc\td");
+    }
+}
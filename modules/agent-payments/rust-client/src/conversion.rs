@@ -0,0 +1,262 @@
+//! Converting token amounts to and from a quoted currency (USD pricing).
+//!
+//! Every conversion rounds exactly once, with an explicit [`Rounding`] mode:
+//! amounts we pay round down, amounts we require to be paid to us round up,
+//! and banker's rounding (half to even) is available when neither side should
+//! be favoured. Rates are decimal strings, and all arithmetic is on exact
+//! integers, so a [`Conversion`] carries the rate, the mode, and the value
+//! before and after rounding, which is enough to reproduce the figure.
+
+use crate::error::coded;
+use alloy::primitives::U256;
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+    HalfEven,
+}
+
+impl Rounding {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "floor" => Ok(Rounding::Floor),
+            "ceil" => Ok(Rounding::Ceil),
+            "half_even" => Ok(Rounding::HalfEven),
+            _ => Err(coded("INVALID_CONVERSION", format!("Unknown rounding '{}': expected floor, ceil or half_even", name))),
+        }
+    }
+
+    /// The mode for a direction of money flow: `pay` rounds down, `receive` rounds up.
+    pub fn for_direction(direction: &str) -> Result<Self> {
+        match direction {
+            "pay" => Ok(Rounding::Floor),
+            "receive" => Ok(Rounding::Ceil),
+            _ => Err(coded("INVALID_CONVERSION", format!("Unknown direction '{}': expected pay or receive", direction))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rounding::Floor => "floor",
+            Rounding::Ceil => "ceil",
+            Rounding::HalfEven => "half_even",
+        }
+    }
+}
+
+/// A non-negative decimal held exactly as `mantissa / 10^scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    pub mantissa: U256,
+    pub scale: u32,
+}
+
+impl Decimal {
+    pub fn parse(text: &str, field: &str) -> Result<Self> {
+        let invalid = |reason: &str| coded("INVALID_CONVERSION", format!("Invalid {} '{}': {}", field, text, reason));
+        if let Some(reason) = crate::amount::locale_format(text) {
+            return Err(invalid(&reason));
+        }
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if whole.is_empty() || !(whole.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit()) {
+            return Err(invalid("expected decimal digits with an optional fractional part"));
+        }
+        let mantissa = U256::from_str_radix(&format!("{}{}", whole, fraction), 10).map_err(|_| invalid("too large"))?;
+        Ok(Decimal {
+            mantissa,
+            scale: u32::try_from(fraction.len()).map_err(|_| invalid("too many decimals"))?,
+        })
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&render(self.mantissa, self.scale))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    pub rate: String,
+    pub rounding: Rounding,
+    /// Exact value before rounding: a decimal when it terminates, otherwise `numerator/denominator`.
+    pub pre_rounding: String,
+    pub post_rounding: String,
+}
+
+impl Conversion {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "rate": self.rate,
+            "rounding": self.rounding.name(),
+            "pre_rounding": self.pre_rounding,
+            "post_rounding": self.post_rounding
+        })
+    }
+}
+
+/// `amount` token base units at `rate` quote units per whole token, to `precision` decimals.
+pub fn to_quote(amount: U256, token_decimals: u32, rate: Decimal, precision: u32, rounding: Rounding) -> Result<Conversion> {
+    let numerator = amount.checked_mul(rate.mantissa).ok_or_else(overflow)?;
+    let denominator = pow10(token_decimals.checked_add(rate.scale).ok_or_else(overflow)?)?;
+    let rounded = round(numerator, denominator, precision, rounding)?;
+    Ok(Conversion {
+        rate: rate.to_string(),
+        rounding,
+        pre_rounding: exact(numerator, denominator)?,
+        post_rounding: render(rounded, precision),
+    })
+}
+
+/// `quote` quote units at `rate` per whole token, to whole token base units.
+pub fn from_quote(quote: Decimal, token_decimals: u32, rate: Decimal, rounding: Rounding) -> Result<Conversion> {
+    if rate.mantissa.is_zero() {
+        return Err(coded("INVALID_CONVERSION", "rate must be greater than zero"));
+    }
+    let shift = pow10(token_decimals.checked_add(rate.scale).ok_or_else(overflow)?)?;
+    let numerator = quote.mantissa.checked_mul(shift).ok_or_else(overflow)?;
+    let denominator = rate.mantissa.checked_mul(pow10(quote.scale)?).ok_or_else(overflow)?;
+    let rounded = round(numerator, denominator, 0, rounding)?;
+    Ok(Conversion {
+        rate: rate.to_string(),
+        rounding,
+        pre_rounding: exact(numerator, denominator)?,
+        post_rounding: rounded.to_string(),
+    })
+}
+
+/// `numerator / denominator` scaled by `10^precision` and rounded to an integer.
+fn round(numerator: U256, denominator: U256, precision: u32, rounding: Rounding) -> Result<U256> {
+    let scaled = numerator.checked_mul(pow10(precision)?).ok_or_else(overflow)?;
+    let (quotient, remainder) = (scaled / denominator, scaled % denominator);
+    let up = match rounding {
+        Rounding::Floor => false,
+        Rounding::Ceil => !remainder.is_zero(),
+        Rounding::HalfEven => {
+            // Compare remainder against half the denominator without doubling past U256::MAX
+            let (half, odd) = (denominator / U256::from(2u64), denominator.bit(0));
+            remainder > half || (remainder == half && !odd && quotient.bit(0))
+        }
+    };
+    if up {
+        quotient.checked_add(U256::from(1u64)).ok_or_else(overflow)
+    } else {
+        Ok(quotient)
+    }
+}
+
+fn exact(numerator: U256, denominator: U256) -> Result<String> {
+    let divisor = numerator.gcd(denominator);
+    let (numerator, denominator) = if divisor.is_zero() {
+        (numerator, denominator)
+    } else {
+        (numerator / divisor, denominator / divisor)
+    };
+
+    // A reduced fraction terminates as a decimal only if its denominator is 2^a * 5^b
+    let (mut rest, mut twos, mut fives) = (denominator, 0u32, 0u32);
+    while !rest.is_zero() && !rest.bit(0) {
+        rest >>= 1;
+        twos += 1;
+    }
+    while !rest.is_zero() && (rest % U256::from(5u64)).is_zero() {
+        rest /= U256::from(5u64);
+        fives += 1;
+    }
+    if rest != U256::from(1u64) {
+        return Ok(format!("{}/{}", numerator, denominator));
+    }
+    let scale = twos.max(fives);
+    let mantissa = numerator.checked_mul(pow10(scale)? / denominator).ok_or_else(overflow)?;
+    Ok(render(mantissa, scale))
+}
+
+fn render(mantissa: U256, scale: u32) -> String {
+    let digits = format!("{:0>width$}", mantissa.to_string(), width = scale as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale as usize);
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+fn pow10(exponent: u32) -> Result<U256> {
+    U256::from(10u64).checked_pow(U256::from(exponent)).ok_or_else(overflow)
+}
+
+fn overflow() -> anyhow::Error {
+    coded("INVALID_CONVERSION", "Conversion exceeds uint256")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::parse(text, "rate").unwrap()
+    }
+
+    fn usd(wei: u64, rate: &str, rounding: Rounding) -> Conversion {
+        to_quote(U256::from(wei), 18, decimal(rate), 2, rounding).unwrap()
+    }
+
+    #[test]
+    fn parses_and_renders_decimals_exactly() {
+        assert_eq!(decimal("2500.10"), Decimal { mantissa: U256::from(250010u64), scale: 2 });
+        assert_eq!(decimal("0.05").to_string(), "0.05");
+        assert_eq!(decimal("7").to_string(), "7");
+        for text in ["", ".5", "1.2.3", "-1", "1e3", "1,000", " 1"] {
+            assert!(Decimal::parse(text, "rate").is_err(), "{:?} was accepted", text);
+        }
+        let err = Decimal::parse("2.500,10", "rate").unwrap_err();
+        assert!(err.to_string().contains("looks like 2500.10 written"), "{}", err);
+    }
+
+    #[test]
+    fn rounding_modes_at_the_boundaries() {
+        // 0.015 ETH at 1 USD/ETH is exactly half a cent
+        let half = 15_000_000_000_000_000;
+        assert_eq!(usd(half, "1", Rounding::Floor).post_rounding, "0.01");
+        assert_eq!(usd(half, "1", Rounding::Ceil).post_rounding, "0.02");
+        assert_eq!(usd(half, "1", Rounding::HalfEven).post_rounding, "0.02");
+        assert_eq!(usd(25_000_000_000_000_000, "1", Rounding::HalfEven).post_rounding, "0.02");
+        assert_eq!(usd(half, "1", Rounding::HalfEven).pre_rounding, "0.015");
+
+        // One wei over and under the half
+        assert_eq!(usd(half + 1, "1", Rounding::HalfEven).post_rounding, "0.02");
+        assert_eq!(usd(25_000_000_000_000_001, "1", Rounding::HalfEven).post_rounding, "0.03");
+        assert_eq!(usd(half - 1, "1", Rounding::HalfEven).post_rounding, "0.01");
+        assert_eq!(usd(1, "1", Rounding::Ceil).post_rounding, "0.01");
+        assert_eq!(usd(1, "1", Rounding::Floor).post_rounding, "0.00");
+
+        // Exact amounts are untouched by every mode
+        for rounding in [Rounding::Floor, Rounding::Ceil, Rounding::HalfEven] {
+            let conversion = usd(2_000_000_000_000_000_000, "2500.10", rounding);
+            assert_eq!(conversion.post_rounding, "5000.20");
+            assert_eq!(conversion.pre_rounding, "5000.2");
+            assert_eq!(usd(0, "2500.10", rounding).post_rounding, "0.00");
+        }
+    }
+
+    #[test]
+    fn quote_to_token_reports_non_terminating_values_as_fractions() {
+        let conversion = from_quote(decimal("10"), 18, decimal("3"), Rounding::Ceil).unwrap();
+        assert_eq!(conversion.pre_rounding, "10000000000000000000/3");
+        assert_eq!(conversion.post_rounding, "3333333333333333334");
+        let conversion = from_quote(decimal("10"), 18, decimal("3"), Rounding::Floor).unwrap();
+        assert_eq!(conversion.post_rounding, "3333333333333333333");
+        let conversion = from_quote(decimal("5000.20"), 18, decimal("2500.10"), Rounding::Floor).unwrap();
+        assert_eq!(conversion.post_rounding, "2000000000000000000");
+        assert!(from_quote(decimal("1"), 18, decimal("0.00"), Rounding::Floor).is_err());
+    }
+
+    #[test]
+    fn overflow_is_an_error_not_a_wrap() {
+        let err = to_quote(U256::MAX, 18, decimal("2"), 2, Rounding::Floor).unwrap_err();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("INVALID_CONVERSION"));
+        assert!(to_quote(U256::from(1u64), 18, decimal("1"), 80, Rounding::Floor).is_err());
+    }
+}
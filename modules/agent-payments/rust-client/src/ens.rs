@@ -0,0 +1,223 @@
+//! ENS names in address arguments.
+//!
+//! An address field holding a name such as `payments.agentco.eth` is either
+//! resolved before the command runs, when `config.resolve_ens` is set, or
+//! rejected with `ENS_NOT_SUPPORTED` instead of failing deep inside the SDK's
+//! address parser. Resolution goes through the ENS registry on
+//! `config.ens_rpc_url`, which is required and must serve Ethereum mainnet:
+//! the 4Mica RPC is a testnet, where the same registry address holds unrelated
+//! records. The name's forward `addr` record is read; names with no address
+//! set are refused. Resolved addresses are cached for `ens_cache_ttl_seconds`
+//! within the run.
+
+use crate::error::coded;
+use alloy::primitives::{address, keccak256, Address, B256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::sol;
+use anyhow::Result;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+sol! {
+    #[sol(rpc)]
+    interface IEnsRegistry {
+        function resolver(bytes32 node) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface IEnsResolver {
+        function addr(bytes32 node) external view returns (address);
+    }
+}
+
+/// Argument keys treated as addresses besides those ending in `address`/`addresses`.
+const ADDRESS_KEYS: &[&str] = &["recipient", "recipients", "user", "operator", "signer"];
+
+pub struct Resolver {
+    provider: DynProvider,
+    ttl: Duration,
+    cache: HashMap<String, (Address, Instant)>,
+    on_mainnet: bool,
+}
+
+impl Resolver {
+    pub fn from_config(config: &serde_json::Value) -> Result<Option<Self>> {
+        if !config["resolve_ens"].as_bool().unwrap_or(false) {
+            return Ok(None);
+        }
+        let rpc_url = config["ens_rpc_url"].as_str().ok_or_else(|| {
+            coded("INVALID_INPUT", "config.resolve_ens needs config.ens_rpc_url pointing at an Ethereum mainnet RPC")
+        })?;
+        Ok(Some(Resolver {
+            provider: ProviderBuilder::new().connect_client(crate::wirelog::rpc_client(rpc_url)?).erased(),
+            ttl: Duration::from_secs(config["ens_cache_ttl_seconds"].as_u64().unwrap_or(300)),
+            cache: HashMap::new(),
+            on_mainnet: false,
+        }))
+    }
+
+    pub async fn resolve(&mut self, name: &str) -> Result<Address> {
+        let name = normalize(name)?;
+        if let Some((address, resolved_at)) = self.cache.get(&name) {
+            if resolved_at.elapsed() < self.ttl {
+                return Ok(*address);
+            }
+        }
+
+        let node = namehash(&name);
+        let failed = |detail: String| coded("ENS_RESOLUTION_FAILED", format!("Cannot resolve {}: {}", name, detail));
+        if !self.on_mainnet {
+            let chain_id =
+                self.provider.get_chain_id().await.map_err(|e| failed(format!("chain id lookup failed: {}", e)))?;
+            if chain_id != 1 {
+                return Err(failed(format!("ens_rpc_url serves chain {}, not Ethereum mainnet", chain_id)));
+            }
+            self.on_mainnet = true;
+        }
+        let resolver = IEnsRegistry::new(ENS_REGISTRY, &self.provider)
+            .resolver(node)
+            .call()
+            .await
+            .map_err(|e| failed(format!("registry lookup failed: {}", e)))?;
+        if resolver.is_zero() {
+            return Err(failed("no resolver is set".to_string()));
+        }
+        let address = IEnsResolver::new(resolver, &self.provider)
+            .addr(node)
+            .call()
+            .await
+            .map_err(|e| failed(format!("resolver {} lookup failed: {}", resolver, e)))?;
+        if address.is_zero() {
+            return Err(failed("the name has no address record".to_string()));
+        }
+
+        self.cache.insert(name, (address, Instant::now()));
+        Ok(address)
+    }
+}
+
+pub fn is_ens_name(value: &str) -> bool {
+    !value.starts_with("0x")
+        && value.contains('.')
+        && value.split('.').all(|label| !label.is_empty())
+        && !value.chars().any(|c| c.is_whitespace() || c == '/' || c == ':')
+}
+
+/// EIP-137 namehash of an already normalized name.
+pub fn namehash(name: &str) -> B256 {
+    name.rsplit('.')
+        .filter(|label| !label.is_empty())
+        .fold(B256::ZERO, |node, label| keccak256([node.as_slice(), keccak256(label).as_slice()].concat()))
+}
+
+/// Lowercases ASCII names; full UTS-46 normalization of other names is not implemented.
+fn normalize(name: &str) -> Result<String> {
+    if !name.is_ascii() {
+        return Err(coded("ENS_RESOLUTION_FAILED", format!("Cannot resolve {}: only ASCII names are supported", name)));
+    }
+    Ok(name.to_ascii_lowercase())
+}
+
+fn is_address_key(key: &str) -> bool {
+    key.ends_with("address") || key.ends_with("addresses") || ADDRESS_KEYS.contains(&key)
+}
+
+/// Replaces ENS names in address fields of `args`, returning `{name, address}` for each replacement.
+pub async fn resolve_args(args: &mut serde_json::Value, resolver: Option<&mut Resolver>) -> Result<Vec<serde_json::Value>> {
+    let mut names = Vec::new();
+    collect_names(args, false, &mut names);
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(resolver) = resolver else {
+        return Err(coded(
+            "ENS_NOT_SUPPORTED",
+            format!("{} is an ENS name; pass a 0x address or set config.resolve_ens", names[0]),
+        ));
+    };
+
+    let mut resolved = HashMap::new();
+    for name in names {
+        if let Entry::Vacant(slot) = resolved.entry(name) {
+            let address = resolver.resolve(slot.key()).await?;
+            slot.insert(address);
+        }
+    }
+    replace_names(args, false, &resolved);
+
+    let mut resolutions: Vec<serde_json::Value> = resolved
+        .into_iter()
+        .map(|(name, address)| serde_json::json!({ "name": name, "address": address.to_string() }))
+        .collect();
+    resolutions.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    Ok(resolutions)
+}
+
+fn collect_names(value: &serde_json::Value, in_address_field: bool, names: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) if in_address_field && is_ens_name(s) => names.push(s.clone()),
+        serde_json::Value::Array(items) => items.iter().for_each(|item| collect_names(item, in_address_field, names)),
+        serde_json::Value::Object(map) => {
+            map.iter().for_each(|(key, item)| collect_names(item, is_address_key(key), names))
+        }
+        _ => {}
+    }
+}
+
+fn replace_names(value: &mut serde_json::Value, in_address_field: bool, resolved: &HashMap<String, Address>) {
+    match value {
+        serde_json::Value::String(s) if in_address_field => {
+            if let Some(address) = resolved.get(s.as_str()) {
+                *value = serde_json::Value::String(address.to_string());
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| replace_names(item, in_address_field, resolved)),
+        serde_json::Value::Object(map) => {
+            map.iter_mut().for_each(|(key, item)| replace_names(item, is_address_key(key), resolved))
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namehash_matches_eip137() {
+        assert_eq!(namehash(""), B256::ZERO);
+        assert_eq!(
+            namehash("eth").to_string(),
+            "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+        assert_eq!(
+            namehash("foo.eth").to_string(),
+            "0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f"
+        );
+    }
+
+    #[tokio::test]
+    async fn names_in_address_fields_are_rejected_without_resolution() {
+        let mut args = serde_json::json!({
+            "memo": "paid to payments.agentco.eth",
+            "claims": { "recipient_address": "0x00000000000000000000000000000000000000b0" }
+        });
+        assert!(resolve_args(&mut args, None).await.unwrap().is_empty());
+
+        let mut args = serde_json::json!({ "claims": { "recipient_address": "payments.agentco.eth" } });
+        let err = resolve_args(&mut args, None).await.unwrap_err();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("ENS_NOT_SUPPORTED"));
+    }
+
+    #[test]
+    fn resolution_needs_an_explicit_mainnet_rpc() {
+        assert!(Resolver::from_config(&serde_json::json!({})).unwrap().is_none());
+        let err = Resolver::from_config(&serde_json::json!({ "resolve_ens": true })).err().unwrap();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("INVALID_INPUT"));
+        let config = serde_json::json!({ "resolve_ens": true, "ens_rpc_url": "http://localhost:8545" });
+        assert!(Resolver::from_config(&config).unwrap().is_some());
+    }
+}
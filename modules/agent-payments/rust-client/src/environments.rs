@@ -0,0 +1,144 @@
+//! Named config bundles selected with `--env <name>`.
+//!
+//! `environments.toml` (or `config.environments_file`) holds one table per
+//! environment, each a full config layer:
+//!
+//! ```toml
+//! [demo]
+//! ethereum_http_rpc_url = "https://demo-rpc.example"
+//! contract_address = "0x..."
+//!
+//! [staging]
+//! ethereum_http_rpc_url = "https://staging-rpc.example"
+//! ```
+//!
+//! The selected environment is the base layer; the Input's `config` is merged
+//! over it key by key, so anything set there still wins. The selected name is
+//! stamped into every Output.
+
+use crate::error::coded;
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::OnceLock;
+
+pub const DEFAULT_FILE: &str = "environments.toml";
+
+/// Config keys whose values are replaced in `list_environments`.
+const SECRET_KEY_PARTS: &[&str] = &["private_key", "secret", "password", "api_key", "token"];
+
+static ACTIVE: OnceLock<String> = OnceLock::new();
+
+/// Name of the environment this run selected, if any.
+pub fn active() -> Option<&'static str> {
+    ACTIVE.get().map(String::as_str)
+}
+
+pub fn file_path(config: &Value) -> &str {
+    config["environments_file"].as_str().unwrap_or(DEFAULT_FILE)
+}
+
+pub fn load(path: &str) -> Result<BTreeMap<String, Value>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| coded("ENVIRONMENTS_FILE_UNREADABLE", format!("Failed to read {}: {}", path, e)))?;
+    parse(&content).map_err(|e| coded("ENVIRONMENTS_FILE_UNREADABLE", format!("Invalid {}: {}", path, e)))
+}
+
+fn parse(content: &str) -> Result<BTreeMap<String, Value>> {
+    let environments: BTreeMap<String, Value> = toml::from_str(content)?;
+    if let Some((name, _)) = environments.iter().find(|(_, layer)| !layer.is_object()) {
+        return Err(anyhow::anyhow!("{} is not a table", name));
+    }
+    Ok(environments)
+}
+
+/// Layers `config` over the named environment and records it as the active one.
+pub fn select(config: &mut Value, name: &str, environments: &BTreeMap<String, Value>) -> Result<()> {
+    let Some(layer) = environments.get(name) else {
+        let available: Vec<&str> = environments.keys().map(String::as_str).collect();
+        return Err(coded(
+            "ENVIRONMENT_NOT_FOUND",
+            format!("No environment named {}; available: {}", name, available.join(", ")),
+        ));
+    };
+    let mut merged = layer.clone();
+    merge(&mut merged, std::mem::take(config));
+    *config = merged;
+    let _ = ACTIVE.set(name.to_string());
+    Ok(())
+}
+
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// A copy of the layer with secret-looking values replaced.
+pub fn redacted(layer: &Value) -> Value {
+    match layer {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let key_lower = key.to_ascii_lowercase();
+                    let value = if SECRET_KEY_PARTS.iter().any(|part| key_lower.contains(part)) {
+                        Value::String("[redacted]".to_string())
+                    } else {
+                        redacted(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redacted).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FILE: &str = r#"
+[demo]
+ethereum_http_rpc_url = "https://demo-rpc.example"
+wallet_private_key = "0xabc"
+wire_log = { enabled = true, file = "demo.log" }
+
+[staging]
+ethereum_http_rpc_url = "https://staging-rpc.example"
+"#;
+
+    #[test]
+    fn input_config_overrides_the_environment_layer() {
+        let environments = parse(FILE).unwrap();
+        let mut config = json!({ "wire_log": { "file": "mine.log" }, "read_only": true });
+        select(&mut config, "demo", &environments).unwrap();
+        assert_eq!(config["ethereum_http_rpc_url"], "https://demo-rpc.example");
+        assert_eq!(config["wire_log"], json!({ "enabled": true, "file": "mine.log" }));
+        assert_eq!(config["read_only"], true);
+        assert_eq!(active(), Some("demo"));
+    }
+
+    #[test]
+    fn undefined_environments_list_the_available_names() {
+        let err = select(&mut json!({}), "prod", &parse(FILE).unwrap()).unwrap_err();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("ENVIRONMENT_NOT_FOUND"));
+        assert!(err.to_string().ends_with("available: demo, staging"));
+        assert!(parse("demo = 1").is_err());
+    }
+
+    #[test]
+    fn listing_redacts_secrets() {
+        let demo = redacted(&parse(FILE).unwrap()["demo"]);
+        assert_eq!(demo["wallet_private_key"], "[redacted]");
+        assert_eq!(demo["ethereum_http_rpc_url"], "https://demo-rpc.example");
+    }
+}
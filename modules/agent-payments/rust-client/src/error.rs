@@ -0,0 +1,30 @@
+//! Errors that carry a stable machine-readable code into `Output::error_code`.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub struct CodedError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+pub fn coded(code: &'static str, message: impl Into<String>) -> anyhow::Error {
+    anyhow::Error::new(CodedError {
+        code,
+        message: message.into(),
+    })
+}
+
+pub fn error_code(error: &anyhow::Error) -> Option<String> {
+    error
+        .downcast_ref::<CodedError>()
+        .map(|e| e.code.to_string())
+}
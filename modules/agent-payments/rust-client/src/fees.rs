@@ -0,0 +1,211 @@
+//! Base fee history for choosing when to send settlement transactions.
+
+use crate::chain::Chain;
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Nominal gas of `payTab` (a transfer plus tab bookkeeping), for estimates
+/// that cover payments not yet built.
+pub const PAY_TAB_GAS: u64 = 80_000;
+
+/// Generous gas allowance for a collateral `deposit`, for checking a wallet
+/// can afford one before funds are committed to it.
+pub const DEPOSIT_GAS: u64 = 100_000;
+
+/// `eth_feeHistory` returns at most this many blocks per call.
+pub const FEE_HISTORY_PAGE: u64 = 1024;
+
+pub struct BaseFeeHistory {
+    /// `(block number, base fee)` for every block in the range, oldest first.
+    pub samples: Vec<(u64, u128)>,
+    /// Median priority fee paid in each block of `samples`.
+    pub median_tips: Vec<u128>,
+    /// Base fee the block after `to_block` will charge.
+    pub next_base_fee: u128,
+}
+
+/// Base fees for `from_block..=to_block`, paging backwards through `eth_feeHistory`.
+pub async fn base_fee_history(chain: &Chain, from_block: u64, to_block: u64) -> Result<BaseFeeHistory> {
+    let mut samples = Vec::new();
+    let mut median_tips = Vec::new();
+    let mut next_base_fee = None;
+    let mut newest = to_block;
+    loop {
+        let count = (newest - from_block + 1).min(FEE_HISTORY_PAGE);
+        let page = chain
+            .provider
+            .get_fee_history(count, BlockNumberOrTag::Number(newest), &[50.0])
+            .await?;
+        // The final entry is the projected fee for the block after `newest`
+        let (projected, fees) = page
+            .base_fee_per_gas
+            .split_last()
+            .ok_or_else(|| anyhow::anyhow!("Empty fee history ending at block {}", newest))?;
+        next_base_fee.get_or_insert(*projected);
+        let rewards = page.reward.unwrap_or_default();
+        for (offset, fee) in fees.iter().enumerate().rev() {
+            samples.push((page.oldest_block + offset as u64, *fee));
+            median_tips.push(rewards.get(offset).and_then(|tips| tips.first()).copied().unwrap_or_default());
+        }
+        if page.oldest_block <= from_block || page.oldest_block == 0 {
+            break;
+        }
+        newest = page.oldest_block - 1;
+    }
+    samples.reverse();
+    median_tips.reverse();
+
+    Ok(BaseFeeHistory {
+        samples,
+        median_tips,
+        next_base_fee: next_base_fee.unwrap_or_default(),
+    })
+}
+
+/// Nearest-rank percentile; `percentile` is clamped to 0..=100.
+pub fn percentile(values: &[u128], percentile: f64) -> Option<u128> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.saturating_sub(1).min(sorted.len() - 1)])
+}
+
+/// Mean base fee per UTC hour of day for `(unix timestamp, base fee)` samples.
+pub fn hourly_averages(samples: &[(u64, u128)]) -> BTreeMap<u8, u128> {
+    let mut sums: BTreeMap<u8, (u128, u128)> = BTreeMap::new();
+    for (timestamp, fee) in samples {
+        let hour = ((timestamp % 86_400) / 3_600) as u8;
+        let entry = sums.entry(hour).or_default();
+        entry.0 = entry.0.saturating_add(*fee);
+        entry.1 += 1;
+    }
+    sums.into_iter().map(|(hour, (sum, count))| (hour, sum / count)).collect()
+}
+
+/// Priority fee percentiles requested from `eth_feeHistory` for confirmation estimates.
+pub const REWARD_PERCENTILES: [f64; 5] = [10.0, 25.0, 50.0, 75.0, 90.0];
+
+/// Blocks to inclusion when a fee clears the matching entry of [`REWARD_PERCENTILES`].
+const BLOCKS_AT_PERCENTILE: [u64; 5] = [10, 5, 3, 2, 1];
+
+/// Blocks to inclusion for a fee that covers the base fee but not even the lowest recent tip.
+const BLOCKS_BELOW_TIPS: u64 = 20;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConfirmationEstimate {
+    pub blocks: u64,
+    /// Highest tip percentile of recent blocks the fee clears over the next base fee.
+    pub percentile: Option<u8>,
+    pub confidence: &'static str,
+}
+
+/// Where `max_fee` falls among recent inclusion prices: the next base fee plus, for each
+/// [`REWARD_PERCENTILES`] entry, that tip's median across blocks. `rewards` holds one row per block.
+/// `None` when the fee is below every recent base fee and may never be included.
+pub fn estimate_confirmation(
+    max_fee: u128,
+    next_base_fee: u128,
+    base_fees: &[u128],
+    rewards: &[Vec<u128>],
+) -> Option<ConfirmationEstimate> {
+    let cleared = (0..REWARD_PERCENTILES.len()).rev().find(|&column| {
+        let tips: Vec<u128> = rewards.iter().filter_map(|row| row.get(column).copied()).collect();
+        percentile(&tips, 50.0).is_some_and(|tip| max_fee >= next_base_fee.saturating_add(tip))
+    });
+    if let Some(column) = cleared {
+        let confidence = match REWARD_PERCENTILES[column] {
+            p if p >= 75.0 => "high",
+            p if p >= 25.0 => "medium",
+            _ => "low",
+        };
+        return Some(ConfirmationEstimate {
+            blocks: BLOCKS_AT_PERCENTILE[column],
+            percentile: Some(REWARD_PERCENTILES[column] as u8),
+            confidence,
+        });
+    }
+    if max_fee >= next_base_fee {
+        return Some(ConfirmationEstimate { blocks: BLOCKS_BELOW_TIPS, percentile: None, confidence: "low" });
+    }
+
+    // Below the current base fee: only includable once it drops back to where it has recently been
+    let affordable = base_fees.iter().filter(|fee| **fee <= max_fee).count() as u64;
+    (affordable > 0).then(|| ConfirmationEstimate {
+        blocks: BLOCKS_BELOW_TIPS.max((base_fees.len() as u64).div_ceil(affordable)),
+        percentile: None,
+        confidence: "low",
+    })
+}
+
+/// What `gas` units cost summed over one payment per block, under each pricing model.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PricingTotals {
+    pub legacy: u128,
+    pub eip1559: u128,
+}
+
+/// Totals for one payment in each block with `(base fee, median tip)`. A legacy bid is first-price
+/// and must carry headroom over the base fee it sees, so it pays `base * (100 + headroom)% + tip`
+/// in full. An EIP-1559 transaction with the same cap is refunded down to `base + tip`.
+pub fn pricing_totals(blocks: &[(u128, u128)], gas: u64, headroom_percent: u32) -> PricingTotals {
+    let mut totals = PricingTotals { legacy: 0, eip1559: 0 };
+    for &(base_fee, tip) in blocks {
+        let bid = base_fee.saturating_mul(100 + u128::from(headroom_percent)) / 100;
+        totals.legacy = totals.legacy.saturating_add(bid.saturating_add(tip).saturating_mul(u128::from(gas)));
+        totals.eip1559 = totals.eip1559.saturating_add(base_fee.saturating_add(tip).saturating_mul(u128::from(gas)));
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let values = [50, 10, 40, 20, 30];
+        assert_eq!(percentile(&values, 0.0), Some(10));
+        assert_eq!(percentile(&values, 20.0), Some(10));
+        assert_eq!(percentile(&values, 25.0), Some(20));
+        assert_eq!(percentile(&values, 50.0), Some(30));
+        assert_eq!(percentile(&values, 100.0), Some(50));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn confirmation_estimates_follow_tip_percentiles() {
+        let base_fees = [100, 100, 120, 80];
+        let rewards = vec![vec![1, 2, 5, 10, 20]; 4];
+        let estimate = |max_fee| estimate_confirmation(max_fee, 100, &base_fees, &rewards);
+
+        assert_eq!(estimate(120), Some(ConfirmationEstimate { blocks: 1, percentile: Some(90), confidence: "high" }));
+        assert_eq!(estimate(105).unwrap().percentile, Some(50));
+        assert_eq!(estimate(105).unwrap().confidence, "medium");
+        assert_eq!(estimate(101).unwrap().confidence, "low");
+        assert_eq!(estimate(100).unwrap().blocks, BLOCKS_BELOW_TIPS);
+        assert_eq!(estimate(90).unwrap().percentile, None);
+        assert_eq!(estimate(50), None);
+    }
+
+    #[test]
+    fn legacy_pays_its_headroom_and_eip1559_is_refunded() {
+        let blocks = [(100, 2), (200, 4)];
+        assert_eq!(pricing_totals(&blocks, 10, 100), PricingTotals { legacy: 6_060, eip1559: 3_060 });
+        assert_eq!(pricing_totals(&blocks, 10, 0), PricingTotals { legacy: 3_060, eip1559: 3_060 });
+        assert_eq!(pricing_totals(&[], 10, 100), PricingTotals { legacy: 0, eip1559: 0 });
+    }
+
+    #[test]
+    fn hourly_averages_bucket_by_utc_hour() {
+        let samples = [(0, 10), (1_800, 30), (3_600, 100), (86_400 + 60, 50)];
+        let averages = hourly_averages(&samples);
+        assert_eq!(averages.get(&0), Some(&30));
+        assert_eq!(averages.get(&1), Some(&100));
+        assert_eq!(averages.len(), 2);
+    }
+}
@@ -0,0 +1,125 @@
+//! Wallet funding checks against the low-water marks in `config.funding`.
+
+use alloy::primitives::U256;
+use anyhow::Result;
+use crate::amount::{format_amount, parse_amount};
+
+pub struct FundingThresholds {
+    pub low_balance_wei: Option<U256>,
+    pub critical_balance_wei: Option<U256>,
+    pub low_collateral_wei: Option<U256>,
+    pub pause_below_critical: bool,
+}
+
+impl FundingThresholds {
+    pub fn from_config(config: &serde_json::Value) -> Result<Self> {
+        let funding = &config["funding"];
+        let threshold = |key: &str| -> Result<Option<U256>> {
+            match &funding[key] {
+                serde_json::Value::Null => Ok(None),
+                value => Ok(Some(parse_amount(value, key)?)),
+            }
+        };
+
+        Ok(FundingThresholds {
+            low_balance_wei: threshold("low_balance_wei")?,
+            critical_balance_wei: threshold("critical_balance_wei")?,
+            low_collateral_wei: threshold("low_collateral_wei")?,
+            pause_below_critical: funding["pause_below_critical"].as_bool().unwrap_or(false),
+        })
+    }
+
+    pub fn is_critical(&self, balance_wei: U256) -> bool {
+        self.critical_balance_wei.is_some_and(|critical| balance_wei < critical)
+    }
+
+    /// Classifies the wallet as `ok`, `low` or `critical` and explains every breached mark.
+    pub fn assess(&self, balance_wei: U256, collateral_wei: U256) -> (&'static str, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut status = "ok";
+
+        if self.is_critical(balance_wei) {
+            status = "critical";
+            warnings.push(format!(
+                "ETH balance {} wei is below the critical threshold {} wei",
+                format_amount(balance_wei),
+                format_amount(self.critical_balance_wei.unwrap_or_default())
+            ));
+        } else if let Some(low) = self.low_balance_wei.filter(|low| balance_wei < *low) {
+            status = "low";
+            warnings.push(format!(
+                "ETH balance {} wei is below the low-water mark {} wei",
+                format_amount(balance_wei),
+                format_amount(low)
+            ));
+        }
+
+        if let Some(low) = self.low_collateral_wei.filter(|low| collateral_wei < *low) {
+            if status == "ok" {
+                status = "low";
+            }
+            warnings.push(format!(
+                "Collateral {} wei is below the low-water mark {} wei",
+                format_amount(collateral_wei),
+                format_amount(low)
+            ));
+        }
+
+        (status, warnings)
+    }
+}
+
+pub struct CollateralRequirement {
+    pub per_tab_reserve: U256,
+    pub creation_fees: U256,
+    pub safety_buffer: U256,
+    pub required: U256,
+}
+
+/// Collateral to back `num_tabs` tabs of up to `max_amount_per_tab` each, plus their
+/// creation fees and a safety margin, but never less than the contract minimum.
+pub fn required_collateral(
+    num_tabs: u64,
+    max_amount_per_tab: U256,
+    creation_fee: U256,
+    safety_margin_basis_points: u64,
+    minimum_collateral: U256,
+) -> Result<CollateralRequirement> {
+    let overflow = || anyhow::anyhow!("Required collateral overflows uint256");
+    let tabs = U256::from(num_tabs);
+    let per_tab_reserve = max_amount_per_tab.checked_mul(tabs).ok_or_else(overflow)?;
+    let creation_fees = creation_fee.checked_mul(tabs).ok_or_else(overflow)?;
+    let subtotal = per_tab_reserve.checked_add(creation_fees).ok_or_else(overflow)?;
+    // Rounded up so the margin is never smaller than asked for
+    let safety_buffer = subtotal
+        .checked_mul(U256::from(safety_margin_basis_points))
+        .ok_or_else(overflow)?
+        .div_ceil(U256::from(10_000u64));
+    let required = subtotal.checked_add(safety_buffer).ok_or_else(overflow)?.max(minimum_collateral);
+
+    Ok(CollateralRequirement {
+        per_tab_reserve,
+        creation_fees,
+        safety_buffer,
+        required,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_collateral_adds_fees_margin_and_respects_the_minimum() {
+        let plan = required_collateral(3, U256::from(1_000u64), U256::from(10u64), 1_050, U256::ZERO).unwrap();
+        assert_eq!(plan.per_tab_reserve, U256::from(3_000u64));
+        assert_eq!(plan.creation_fees, U256::from(30u64));
+        assert_eq!(plan.safety_buffer, U256::from(319u64));
+        assert_eq!(plan.required, U256::from(3_349u64));
+
+        let plan = required_collateral(0, U256::from(1_000u64), U256::from(10u64), 0, U256::from(500u64)).unwrap();
+        assert_eq!(plan.required, U256::from(500u64));
+
+        assert!(required_collateral(2, U256::MAX, U256::ZERO, 0, U256::ZERO).is_err());
+    }
+}
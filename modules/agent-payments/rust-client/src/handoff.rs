@@ -0,0 +1,19 @@
+//! Checkpoint fields for `transfer_collateral_to_new_agent`.
+//!
+//! The handoff spans the contract's withdrawal delay, which can outlast any
+//! single run, so each phase is recorded in a [`Checkpoint`] as it completes.
+//! A re-run with the same state file skips finished phases instead of
+//! requesting, withdrawing or depositing twice.
+
+use crate::checkpoint::Checkpoint;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Handoff {
+    pub old_agent_address: Option<String>,
+    pub new_agent_address: Option<String>,
+    /// Collateral being moved, fixed when the withdrawal is requested.
+    pub amount_wei: Option<String>,
+}
+
+pub type HandoffState = Checkpoint<Handoff>;
@@ -0,0 +1,172 @@
+//! Reading and validating the Input file before any command runs.
+//!
+//! Inputs are frequently generated by LLM agents, so everything here must turn
+//! malformed content into a coded error rather than a panic or an unbounded
+//! allocation.
+
+use crate::error::coded;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Largest Input file accepted, checked before the file is read.
+pub const MAX_INPUT_BYTES: u64 = 1024 * 1024;
+
+/// Deepest array/object nesting accepted anywhere in the Input.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Input {
+    pub command: String,
+    pub args: serde_json::Value,
+    pub config: serde_json::Value,
+}
+
+pub fn read_input(path: &str) -> Result<Input> {
+    let size = fs::metadata(path)
+        .map_err(|e| coded("INVALID_INPUT", format!("Failed to read input file {}: {}", path, e)))?
+        .len();
+    if size > MAX_INPUT_BYTES {
+        return Err(too_large(size));
+    }
+
+    let bytes = fs::read(path)
+        .map_err(|e| coded("INVALID_INPUT", format!("Failed to read input file {}: {}", path, e)))?;
+    let content = String::from_utf8(bytes)
+        .map_err(|e| coded("INVALID_INPUT", format!("Input file is not valid UTF-8: {}", e)))?;
+    parse_input(&content)
+}
+
+pub fn parse_input(content: &str) -> Result<Input> {
+    if content.len() as u64 > MAX_INPUT_BYTES {
+        return Err(too_large(content.len() as u64));
+    }
+    let depth = nesting_depth(content);
+    if depth > MAX_NESTING_DEPTH {
+        return Err(coded(
+            "INPUT_TOO_DEEP",
+            format!("Input nests {} levels deep, the maximum is {}", depth, MAX_NESTING_DEPTH),
+        ));
+    }
+
+    let mut input: Input = serde_json::from_str(content)
+        .map_err(|e| coded("INVALID_INPUT", format!("Invalid input JSON: {}", e)))?;
+    // Commands index into args/config freely, so normalize absent sections to empty objects
+    if input.args.is_null() {
+        input.args = serde_json::json!({});
+    }
+    if input.config.is_null() {
+        input.config = serde_json::json!({});
+    }
+    Ok(input)
+}
+
+fn too_large(size: u64) -> anyhow::Error {
+    coded(
+        "INPUT_TOO_LARGE",
+        format!("Input is {} bytes, the maximum is {}", size, MAX_INPUT_BYTES),
+    )
+}
+
+/// Maximum array/object depth, scanned without parsing so hostile input cannot recurse.
+fn nesting_depth(content: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for byte in content.bytes() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::error_code;
+    use proptest::prelude::*;
+
+    fn arb_json() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(serde_json::Value::Null),
+            any::<bool>().prop_map(serde_json::Value::from),
+            any::<i64>().prop_map(serde_json::Value::from),
+            any::<f64>().prop_map(serde_json::Value::from),
+            ".*".prop_map(serde_json::Value::from),
+        ];
+        leaf.prop_recursive(8, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(serde_json::Value::from),
+                prop::collection::btree_map(".*", inner, 0..8)
+                    .prop_map(|m| serde_json::Value::Object(m.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn assert_coded(result: Result<Input>) {
+        if let Err(e) = result {
+            let code = error_code(&e).expect("parse errors carry a code");
+            assert!(["INVALID_INPUT", "INPUT_TOO_LARGE", "INPUT_TOO_DEEP"].contains(&code.as_str()));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_text_never_panics(content in ".*") {
+            assert_coded(parse_input(&content));
+        }
+
+        #[test]
+        fn arbitrary_json_never_panics(value in arb_json()) {
+            assert_coded(parse_input(&value.to_string()));
+        }
+
+        #[test]
+        fn well_formed_inputs_round_trip(command in ".*", args in arb_json(), config in arb_json()) {
+            let content = serde_json::json!({ "command": command, "args": args, "config": config });
+            let input = parse_input(&content.to_string()).unwrap();
+            prop_assert_eq!(input.command, command);
+            prop_assert!(!input.args.is_null() && !input.config.is_null());
+        }
+    }
+
+    #[test]
+    fn rejects_deep_nesting_before_parsing() {
+        let content = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(error_code(&parse_input(&content).unwrap_err()).as_deref(), Some("INPUT_TOO_DEEP"));
+    }
+
+    #[test]
+    fn brackets_inside_strings_do_not_count_as_nesting() {
+        let content = format!(
+            r#"{{"command":"get_user","args":{{"note":"{}\"{}"}},"config":{{}}}}"#,
+            "[".repeat(100),
+            "{".repeat(100)
+        );
+        assert!(parse_input(&content).is_ok());
+    }
+
+    #[test]
+    fn rejects_oversized_input() {
+        let content = " ".repeat(MAX_INPUT_BYTES as usize + 1);
+        assert_eq!(error_code(&parse_input(&content).unwrap_err()).as_deref(), Some("INPUT_TOO_LARGE"));
+    }
+}
@@ -5,27 +5,77 @@ use std::env;
 use std::fs;
 use std::str::FromStr;
 use anyhow::Result;
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Input {
-    command: String,
-    args: serde_json::Value,
-    config: serde_json::Value,
-}
+mod amount;
+mod chain;
+mod bootstrap;
+mod bundle;
+mod certstore;
+mod checkpoint;
+mod commands;
+mod compare;
+mod compat;
+mod console;
+mod conversion;
+mod ens;
+mod environments;
+mod error;
+mod fees;
+mod funding;
+mod handoff;
+mod input;
+mod outcome;
+mod provenance;
+mod redaction;
+mod topup;
+mod typed_data;
+mod verification;
+mod voucher;
+mod webhook;
+mod wirelog;
+mod workbinding;
+
+use amount::{format_amount, parse_amount, parse_amount_or, parse_amount_str};
+use chain::Chain;
+use commands::CommandKind;
+use console::{estatus, status};
+use error::coded;
+use funding::FundingThresholds;
+use input::Input;
+use outcome::{PartialFailure, Step};
+
+const DEFAULT_WALLET_PRIVATE_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+const DEFAULT_API_URL: &str = "https://api.4mica.xyz";
+const DEFAULT_ETHEREUM_HTTP_RPC_URL: &str = "https://ethereum-holesky.publicnode.com";
+const DEFAULT_CONTRACT_ADDRESS: &str = "0x698B98d6574dE06dD39A49Cc4e37f3B06d454Eb9";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Output {
     success: bool,
     error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partial: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    steps: Option<Vec<Step>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resume: Option<Vec<serde_json::Value>>,
+    /// Set when `--env` selected an environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<&'static str>,
     #[serde(flatten)]
     data: serde_json::Value,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    env_logger::init();
+    let (env_name, args) = split_env_flag(env::args().collect());
     if args.len() != 3 {
-        eprintln!("Usage: {} <input_file> <output_file>", args[0]);
+        eprintln!("Usage: {} [--env <name>] <input_file> <output_file>", args[0]);
         std::process::exit(1);
     }
 
@@ -33,77 +83,451 @@ async fn main() -> Result<()> {
     let output_file = &args[2];
 
     // Read input
-    let input_content = fs::read_to_string(input_file)?;
-    let input: Input = serde_json::from_str(&input_content)?;
+    let mut input = match input::read_input(input_file) {
+        Ok(input) => input,
+        Err(e) => return write_output(output_file, Err(e)),
+    };
+    if let Some(name) = env_name {
+        let selected = environments::load(environments::file_path(&input.config))
+            .and_then(|defined| environments::select(&mut input.config, &name, &defined));
+        if let Err(e) = selected {
+            return write_output(output_file, Err(e));
+        }
+    }
 
-    // Create 4Mica client using real SDK - force all config values to avoid API parsing
-    let config = ConfigBuilder::default()
-        .rpc_url(input.config["rpc_url"].as_str().unwrap_or_else(|| "https://api.4mica.xyz").to_string())
-        .wallet_private_key(input.config["wallet_private_key"].as_str().unwrap_or_else(|| "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_string())
-        .ethereum_http_rpc_url(input.config["ethereum_http_rpc_url"].as_str().unwrap_or_else(|| "https://ethereum-holesky.publicnode.com").to_string())
-        .contract_address(input.config["contract_address"].as_str().unwrap_or_else(|| "0x698B98d6574dE06dD39A49Cc4e37f3B06d454Eb9").to_string())
-        .build()
-        .map_err(|e| anyhow::anyhow!("Config build failed: {}", e))?;
-    
-    let client = match Client::new(config).await {
-        Ok(client) => client,
-        Err(e) => {
-            let output = Output {
-                success: false,
-                error: Some(format!("Failed to create client: {}", e)),
-                data: serde_json::Value::Null,
-            };
-            fs::write(output_file, serde_json::to_string_pretty(&output)?)?;
-            return Ok(());
+    console::set_ascii_only(input.config["log_ascii"].as_bool().unwrap_or(false));
+    wirelog::configure(&input.config);
+    if let Err(e) = redaction::configure(&input.config) {
+        return write_output(output_file, Err(e));
+    }
+    if let Err(e) = provenance::enforce(&input.config) {
+        return write_output(output_file, Err(e));
+    }
+
+    let kind = match commands::command_kind(&input.command) {
+        Some(kind) => kind,
+        None => return write_output(output_file, Err(anyhow::anyhow!("Unknown command: {}", input.command))),
+    };
+    if kind != CommandKind::Read && input.config["read_only"].as_bool().unwrap_or(false) {
+        return write_output(
+            output_file,
+            Err(coded("READ_ONLY", format!("{} is not allowed in read_only mode", input.command))),
+        );
+    }
+
+    // Resolve or reject ENS names before anything tries to parse them as addresses
+    let ens_resolutions = match resolve_ens_names(&mut input).await {
+        Ok(resolutions) => resolutions,
+        Err(e) => return write_output(output_file, Err(e)),
+    };
+
+    let wallet_private_key = input.config["wallet_private_key"].as_str().unwrap_or(DEFAULT_WALLET_PRIVATE_KEY);
+
+    // Local commands run before any client is built so they work without network access
+    if let Some(result) = run_offline_command(&input, wallet_private_key) {
+        // compare_outputs exits non-zero on differences so it can gate deployments
+        let differs = input.command == "compare_outputs"
+            && result.as_ref().is_ok_and(|data| data["identical"] == false);
+        write_output(output_file, with_ens_resolutions(result, ens_resolutions))?;
+        if differs {
+            std::process::exit(1);
         }
+        return Ok(());
+    }
+
+    let (client, chain) = match connect(&input.config, wallet_private_key).await {
+        Ok(connected) => connected,
+        Err(e) => return write_output(output_file, Err(e)),
     };
 
+    // Refuse to spend gas while the wallet is below its critical funding mark
+    if let Err(e) = check_funding_floor(&chain, &input, kind).await {
+        return write_output(output_file, Err(e));
+    }
+
+    let topup = run_auto_topup(&client, &chain, &input).await;
+
     // Execute command
     let result = match input.command.as_str() {
         "test_connection" => test_connection().await,
         "deposit" => deposit(&client, &input.args).await,
         "get_user" => get_user(&client).await,
         "create_tab" => create_tab(&client, &input.args).await,
-        "sign_payment" => sign_payment(&client, &input.args).await,
+        "sign_payment" => sign_payment(&client, wallet_private_key, &input.args).await,
+        "sign_multiple_req_ids" => sign_multiple_req_ids(&client, wallet_private_key, &input.args).await,
         "issue_payment_guarantee" => issue_payment_guarantee(&client, &input.args).await,
         "pay_tab" => pay_tab(&client, &input.args).await,
         "get_tab_payment_status" => get_tab_payment_status(&client, &input.args).await,
         "remunerate" => remunerate(&client, &input.args).await,
         "verify_bls_signature" => verify_bls_signature(&client, &input.args).await,
-        _ => {
-            let output = Output {
-                success: false,
-                error: Some(format!("Unknown command: {}", input.command)),
-                data: serde_json::Value::Null,
-            };
-            fs::write(output_file, serde_json::to_string_pretty(&output)?)?;
-            return Ok(());
+        "batch_create_tabs_and_sign" => batch_create_tabs_and_sign(&client, &input.args).await,
+        "sign_payment_for_operator" => sign_payment_for_operator(&client, &chain, &input.args).await,
+        "get_funding_status" => get_funding_status(&client, &chain, &input.config).await,
+        "bootstrap" => bootstrap(&input.config, &input.args).await,
+        "suggest_settlement_window" => suggest_settlement_window(&chain, &input.args).await,
+        "get_tab_settlement_status" => get_tab_settlement_status(&client, &input.args).await,
+        "create_voucher" => create_voucher(&client, wallet_private_key, &input.args).await,
+        "sign_payment_ecdsa_raw" => sign_payment_ecdsa_raw(&client, wallet_private_key, &input.args).await,
+        "simulate_flow" => simulate_flow(&client, &chain, &input.args).await,
+        "get_contract_upgrade_status" => get_contract_upgrade_status(&chain).await,
+        "benchmark_rpc" => benchmark_rpc(&chain, &input.args).await,
+        "get_estimated_confirmation_time" => get_estimated_confirmation_time(&chain, &input.args).await,
+        "get_eip712_domain_separator" => get_eip712_domain_separator(&chain, &input.args).await,
+        "create_signed_tab_bundle" => create_signed_tab_bundle(&client, wallet_private_key, &input.args).await,
+        "load_signed_tab_bundle" => load_signed_tab_bundle(&client, &input.args).await,
+        "get_gas_savings_estimate" => get_gas_savings_estimate(&chain, &input.args).await,
+        "export_certs" => export_certs(&client, wallet_private_key, &input.args).await,
+        "transfer_collateral_to_new_agent" => {
+            transfer_collateral_to_new_agent(&client, &chain, &input.config, &input.args).await
+        }
+        _ => Err(anyhow::anyhow!("Unknown command: {}", input.command)),
+    };
+
+    let result = match result {
+        Ok(data) if kind == CommandKind::ChainWrite => verify_receipt(&chain, &input, data).await,
+        result => result,
+    };
+
+    let result = match (result, topup) {
+        (Ok(mut data), Some(topup)) => {
+            data["auto_topup"] = topup;
+            Ok(data)
         }
+        (Err(e), Some(topup)) => Err(with_note(
+            e,
+            format!("auto top-up {}: {}", topup["status"].as_str().unwrap_or(""), topup["detail"].as_str().unwrap_or("")),
+        )),
+        (result, None) => result,
     };
 
-    // Write output
+    write_output(output_file, with_ens_resolutions(result, ens_resolutions))
+}
+
+async fn resolve_ens_names(input: &mut Input) -> Result<Vec<serde_json::Value>> {
+    let mut resolver = ens::Resolver::from_config(&input.config)?;
+    ens::resolve_args(&mut input.args, resolver.as_mut()).await
+}
+
+/// Records which names the command's addresses came from.
+fn with_ens_resolutions(result: Result<serde_json::Value>, resolutions: Vec<serde_json::Value>) -> Result<serde_json::Value> {
     match result {
-        Ok(data) => {
-            let output = Output {
-                success: true,
-                error: None,
-                data,
-            };
-            fs::write(output_file, serde_json::to_string_pretty(&output)?)?;
+        Ok(mut data) if !resolutions.is_empty() => {
+            data["ens_resolutions"] = serde_json::Value::from(resolutions);
+            Ok(data)
         }
-        Err(e) => {
-            let output = Output {
-                success: false,
-                error: Some(e.to_string()),
-                data: serde_json::Value::Null,
-            };
-            fs::write(output_file, serde_json::to_string_pretty(&output)?)?;
+        result => result,
+    }
+}
+
+/// Deposits collateral per `config.auto_topup`; the record is `None` when nothing was attempted.
+async fn run_auto_topup(client: &Client, chain: &Chain, input: &Input) -> Option<serde_json::Value> {
+    if !commands::COLLATERAL_COMMANDS.contains(&input.command.as_str()) || input.config["read_only"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    let policy = match topup::AutoTopup::from_config(&input.config) {
+        Ok(Some(policy)) => policy,
+        Ok(None) => return None,
+        Err(e) => return Some(serde_json::json!({ "reason": "auto_topup", "status": "failed", "detail": e.to_string() })),
+    };
+
+    let attempt = async {
+        let collateral = client.user.get_user().await
+            .map_err(|e| anyhow::anyhow!("Get user failed: {}", e))?
+            .collateral;
+        if collateral >= policy.min_collateral_wei {
+            return Ok(None);
+        }
+        let balance = chain.provider.get_balance(chain.wallet_address).await?;
+        let now = unix_timestamp()?;
+        let deposited_last_day = policy.deposited_last_day(now)?;
+
+        let record = match policy.plan(collateral, balance, deposited_last_day) {
+            topup::Plan::NotNeeded => return Ok(None),
+            topup::Plan::Blocked(detail) => serde_json::json!({ "status": "blocked", "detail": detail }),
+            topup::Plan::Deposit(amount) => {
+                // The deposit is a chain write of its own, held to the same floor and verification
+                if let Err(e) = enforce_funding_floor(chain, &input.config, "auto_topup").await {
+                    return Ok(Some(serde_json::json!({
+                        "status": "blocked",
+                        "detail": e.to_string(),
+                        "error_code": error::error_code(&e)
+                    })));
+                }
+                estatus!("💰 Auto top-up: depositing {} wei of collateral", format_amount(amount));
+                let receipt = client.user.deposit(amount).await
+                    .map_err(|e| anyhow::anyhow!("Deposit of {} wei failed: {}", amount, e))?;
+                let mut record = serde_json::json!({
+                    "status": "deposited",
+                    "detail": format!("deposited {} wei", format_amount(amount)),
+                    "amount_wei": format_amount(amount),
+                    "transaction_hash": receipt.transaction_hash
+                });
+                // The deposit went through either way; an unrecorded one only loosens the daily cap
+                if let Err(e) = policy.record(now, amount) {
+                    record["ledger_error"] = serde_json::json!(e.to_string());
+                }
+                match receipt_verdict(chain, &input.config, &record).await {
+                    Ok(None) => {}
+                    Ok(Some(verification::Verdict::Match(details))) => record["receipt_verification"] = details,
+                    Ok(Some(verification::Verdict::Mismatch(details))) => {
+                        record["status"] = serde_json::json!("unverified");
+                        record["error_code"] = serde_json::json!("VERIFICATION_MISMATCH");
+                        record["receipt_verification"] = details;
+                    }
+                    Ok(Some(verification::Verdict::Unavailable(reason))) => {
+                        record["status"] = serde_json::json!("unverified");
+                        record["error_code"] = serde_json::json!("VERIFICATION_UNAVAILABLE");
+                        record["detail"] = serde_json::json!(reason);
+                    }
+                    Err(e) => {
+                        record["status"] = serde_json::json!("unverified");
+                        record["error_code"] = serde_json::json!("VERIFICATION_UNAVAILABLE");
+                        record["detail"] = serde_json::json!(e.to_string());
+                    }
+                }
+                record
+            }
+        };
+        Ok::<_, anyhow::Error>(Some(record))
+    };
+
+    let mut record = match attempt.await {
+        Ok(Some(record)) => record,
+        Ok(None) => return None,
+        Err(e) => serde_json::json!({ "status": "failed", "detail": e.to_string() }),
+    };
+    record["reason"] = serde_json::json!("auto_topup");
+    Some(record)
+}
+
+/// Re-checks a write's receipt against `config.verify_rpc_urls` before reporting success.
+async fn verify_receipt(chain: &Chain, input: &Input, data: serde_json::Value) -> Result<serde_json::Value> {
+    let verdict = match receipt_verdict(chain, &input.config, &data).await {
+        Ok(None) => return Ok(data),
+        Ok(Some(verdict)) => verdict,
+        Err(e) => verification::Verdict::Unavailable(e.to_string()),
+    };
+
+    let (code, message, details) = match verdict {
+        verification::Verdict::Match(details) => {
+            let mut data = data;
+            data["receipt_verification"] = details;
+            return Ok(data);
+        }
+        verification::Verdict::Mismatch(details) => (
+            "VERIFICATION_MISMATCH",
+            format!("Verification node disagrees with the primary about {}", data["transaction_hash"]),
+            details,
+        ),
+        verification::Verdict::Unavailable(reason) => (
+            "VERIFICATION_UNAVAILABLE",
+            format!("Could not verify {}: {}", data["transaction_hash"], reason),
+            serde_json::Value::Null,
+        ),
+    };
+
+    // The transaction is already on chain, so report it as committed but unconfirmed
+    Err(PartialFailure {
+        steps: vec![
+            Step::completed(input.command.clone(), data),
+            Step::failed("verify_receipt", code, message),
+        ],
+        resume: Vec::new(),
+        data: serde_json::json!({ "receipt_verification": details }),
+    }
+    .into())
+}
+
+/// `None` when verification is off, the write is below the value threshold, or it has no single receipt.
+async fn receipt_verdict(
+    chain: &Chain,
+    config: &serde_json::Value,
+    data: &serde_json::Value,
+) -> Result<Option<verification::Verdict>> {
+    let Some(verifier) = verification::ReceiptVerifier::from_config(config)? else {
+        return Ok(None);
+    };
+    // Multi-transaction commands (bootstrap) report hashes per phase rather than at the top level
+    let Some(tx_hash) = data["transaction_hash"].as_str() else {
+        return Ok(None);
+    };
+    let tx_hash = alloy::primitives::B256::from_str(tx_hash)?;
+
+    let receipt = chain.provider.get_transaction_receipt(tx_hash).await?
+        .ok_or_else(|| anyhow::anyhow!("Receipt for {} not found on the primary node", tx_hash))?;
+    if verification::transaction_value(chain, &receipt).await? < verifier.min_value_wei {
+        return Ok(None);
+    }
+    Ok(Some(verifier.verify(&receipt).await))
+}
+
+/// Appends context to an error message, keeping its error code.
+fn with_note(error: anyhow::Error, note: String) -> anyhow::Error {
+    if error.downcast_ref::<PartialFailure>().is_some() {
+        return error;
+    }
+    match error.downcast_ref::<error::CodedError>() {
+        Some(coded_error) => coded(coded_error.code, format!("{} ({})", coded_error.message, note)),
+        None => anyhow::anyhow!("{} ({})", error, note),
+    }
+}
+
+/// Runs commands that need no network access; `None` means the command needs a client.
+fn run_offline_command(input: &Input, wallet_private_key: &str) -> Option<Result<serde_json::Value>> {
+    match input.command.as_str() {
+        "verify_certificate_quorum" => Some(verify_certificate_quorum(&input.args)),
+        "compare_outputs" => Some(compare_outputs(&input.args)),
+        "sign_typed_data" => Some(sign_typed_data(wallet_private_key, &input.args)),
+        "verify_typed_data" => Some(verify_typed_data(wallet_private_key, &input.args)),
+        "verify_eip191_signature" => Some(verify_eip191_signature(&input.args)),
+        "verify_webhook_signature" => Some(verify_webhook_signature(&input.args)),
+        "verify_payment_signature" => Some(verify_payment_signature(&input.args)),
+        "verify_work_binding" => Some(verify_work_binding(&input.args)),
+        "export_wallet" => Some(export_wallet(wallet_private_key)),
+        "format_amount" => Some(format_amount_command(&input.args)),
+        "convert_amount" => Some(convert_amount(&input.args, &input.config)),
+        "list_environments" => Some(list_environments(&input.config)),
+        "compat_report" => Some(compat_report(&input.args)),
+        "redact_output" => Some(redact_output(&input.args, &input.config)),
+        "verify_build" => Some(verify_build(&input.config)),
+        "import_certs" => Some(import_certs(wallet_private_key, &input.args)),
+        "compute_required_collateral" => Some(compute_required_collateral(&input.args)),
+        _ => None,
+    }
+}
+
+/// Builds the SDK client and the direct chain handle for one wallet.
+async fn connect(config: &serde_json::Value, wallet_private_key: &str) -> Result<(Client, Chain)> {
+    let ethereum_http_rpc_url = config["ethereum_http_rpc_url"].as_str().unwrap_or(DEFAULT_ETHEREUM_HTTP_RPC_URL);
+    let contract_address = config["contract_address"].as_str().unwrap_or(DEFAULT_CONTRACT_ADDRESS);
+
+    // Create 4Mica client using real SDK - force all config values to avoid API parsing
+    let sdk_config = ConfigBuilder::default()
+        .rpc_url(config["rpc_url"].as_str().unwrap_or(DEFAULT_API_URL).to_string())
+        .wallet_private_key(wallet_private_key.to_string())
+        .ethereum_http_rpc_url(ethereum_http_rpc_url.to_string())
+        .contract_address(contract_address.to_string())
+        .build()
+        .map_err(|e| anyhow::anyhow!("Config build failed: {}", e))?;
+
+    let client = Client::new(sdk_config).await
+        .map_err(|e| anyhow::anyhow!("Failed to create client: {}", e))?;
+
+    // Direct chain access for reads the SDK does not cover
+    let chain = Chain::new(ethereum_http_rpc_url, contract_address, wallet_private_key)
+        .map_err(|e| anyhow::anyhow!("Failed to create chain client: {}", e))?;
+
+    if wirelog::bodies_requested() {
+        let chain_id = chain.provider.get_chain_id().await
+            .map_err(|e| anyhow::anyhow!("Get chain id failed: {}", e))?;
+        wirelog::allow_bodies(chain_id)?;
+    }
+
+    Ok((client, chain))
+}
+
+/// Removes `--env <name>` (or `--env=<name>`) from the command line, leaving the positional arguments.
+fn split_env_flag(args: Vec<String>) -> (Option<String>, Vec<String>) {
+    let (mut env_name, mut positional) = (None, Vec::new());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--env" {
+            env_name = args.next();
+        } else if let Some(name) = arg.strip_prefix("--env=") {
+            env_name = Some(name.to_string());
+        } else {
+            positional.push(arg);
         }
     }
+    (env_name, positional)
+}
 
+fn write_output(output_file: &str, result: Result<serde_json::Value>) -> Result<()> {
+    fs::write(output_file, render_output(result))?;
     Ok(())
 }
 
+/// Serializes a command result as an Output document; never fails.
+fn render_output(result: Result<serde_json::Value>) -> String {
+    let output = match result {
+        Ok(data) => Output {
+            success: true,
+            error: None,
+            error_code: None,
+            partial: None,
+            steps: None,
+            resume: None,
+            environment: environments::active(),
+            data,
+        },
+        Err(e) => match e.downcast::<PartialFailure>() {
+            Ok(partial) => Output {
+                success: false,
+                error: Some(partial.to_string()),
+                error_code: partial.first_failed().and_then(|step| step.error_code.clone()),
+                partial: Some(true),
+                steps: Some(partial.steps),
+                resume: Some(partial.resume),
+                environment: environments::active(),
+                data: partial.data,
+            },
+            Err(e) => Output {
+                success: false,
+                error: Some(e.to_string()),
+                error_code: error::error_code(&e),
+                partial: None,
+                steps: None,
+                resume: None,
+                environment: environments::active(),
+                data: serde_json::Value::Null,
+            },
+        },
+    };
+    serde_json::to_value(&output)
+        .and_then(|mut output| {
+            redaction::apply_inline(&mut output);
+            serde_json::to_string_pretty(&output)
+        })
+        .unwrap_or_else(|e| {
+            // Only reachable if a handler returned non-object data
+            serde_json::json!({
+                "success": false,
+                "error": format!("Failed to serialize output: {}", e),
+                "error_code": "INTERNAL_ERROR"
+            })
+            .to_string()
+        })
+}
+
+fn parse_claims(claims_json: &serde_json::Value) -> Result<PaymentGuaranteeClaims> {
+    Ok(PaymentGuaranteeClaims {
+        user_address: claims_json["user_address"].as_str().unwrap_or("").to_string(),
+        recipient_address: claims_json["recipient_address"].as_str().unwrap_or("").to_string(),
+        tab_id: parse_id(&claims_json["tab_id"], "claims.tab_id")?,
+        req_id: parse_id(&claims_json["req_id"], "claims.req_id")?,
+        amount: parse_amount(&claims_json["amount"], "claims.amount")?,
+        timestamp: claims_json["timestamp"].as_u64().unwrap_or(0),
+    })
+}
+
+/// Parses a required tab or request id given as a decimal or 0x-hex string.
+fn parse_id(value: &serde_json::Value, field: &str) -> Result<U256> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| coded("INVALID_INPUT", format!("{} is required as a decimal or 0x-hex string", field)))?;
+    U256::from_str(text).map_err(|e| coded("INVALID_INPUT", format!("Invalid {} '{}': {}", field, text, e)))
+}
+
+fn parse_signing_scheme(scheme: &str) -> SigningScheme {
+    match scheme {
+        "Eip191" => SigningScheme::Eip191,
+        _ => SigningScheme::Eip712,
+    }
+}
+
+fn unix_timestamp() -> Result<u64> {
+    Ok(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs())
+}
 
 async fn test_connection() -> Result<serde_json::Value> {
     Ok(serde_json::json!({
@@ -112,8 +536,7 @@ async fn test_connection() -> Result<serde_json::Value> {
 }
 
 async fn deposit(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
-    let amount_str = args["amount"].as_str().unwrap_or("0");
-    let amount = U256::from_str(amount_str)?;
+    let amount = parse_amount(&args["amount"], "amount")?;
     
     match client.user.deposit(amount).await {
         Ok(receipt) => Ok(serde_json::json!({
@@ -140,12 +563,14 @@ async fn create_tab(client: &Client, args: &serde_json::Value) -> Result<serde_j
     let user_address = args["user_address"].as_str().unwrap_or("");
     let recipient_address = args["recipient_address"].as_str().unwrap_or("");
     let ttl = args["ttl"].as_u64();
-    
-    match client.recipient.create_tab(
-        user_address.to_string(),
-        recipient_address.to_string(),
-        ttl
-    ).await {
+
+    let request = serde_json::json!({
+        "user_address": user_address,
+        "recipient_address": recipient_address,
+        "ttl": ttl
+    });
+    let created = client.recipient.create_tab(user_address.to_string(), recipient_address.to_string(), ttl);
+    match wirelog::api_call("create_tab", request, created).await {
         Ok(tab_id) => Ok(serde_json::json!({
             "tab_id": tab_id.to_string()
         })),
@@ -153,66 +578,214 @@ async fn create_tab(client: &Client, args: &serde_json::Value) -> Result<serde_j
     }
 }
 
-async fn sign_payment(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
-    let claims_json = &args["claims"];
-    let claims = PaymentGuaranteeClaims {
-        user_address: claims_json["user_address"].as_str().unwrap_or("").to_string(),
-        recipient_address: claims_json["recipient_address"].as_str().unwrap_or("").to_string(),
-        tab_id: U256::from_str(claims_json["tab_id"].as_str().unwrap_or("0"))?,
-        req_id: U256::from_str(claims_json["req_id"].as_str().unwrap_or("0"))?,
-        amount: U256::from_str(claims_json["amount"].as_str().unwrap_or("0"))?,
-        timestamp: claims_json["timestamp"].as_u64().unwrap_or(0),
-    };
+/// Collateral for `num_tabs` tabs. The contract's minimum collateral and tab creation fee are not read from
+/// the deployment, so callers pass them as `minimum_collateral_wei` and `tab_creation_fee_wei` (default 0).
+fn compute_required_collateral(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let num_tabs = args["num_tabs"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("num_tabs must be a non-negative integer"))?;
+    let max_amount_per_tab = parse_amount(&args["max_amount_per_tab_wei"], "max_amount_per_tab_wei")?;
+    let margin_percent = args["safety_margin_percent"].as_f64().unwrap_or(0.0);
+    if !margin_percent.is_finite() || !(0.0..=10_000.0).contains(&margin_percent) {
+        return Err(anyhow::anyhow!("safety_margin_percent must be between 0 and 10000, got {}", margin_percent));
+    }
+
+    let minimum_collateral = parse_amount_or(&args["minimum_collateral_wei"], "minimum_collateral_wei", U256::ZERO)?;
+    let creation_fee = parse_amount_or(&args["tab_creation_fee_wei"], "tab_creation_fee_wei", U256::ZERO)?;
+
+    let plan = funding::required_collateral(
+        num_tabs,
+        max_amount_per_tab,
+        creation_fee,
+        (margin_percent * 100.0).round() as u64,
+        minimum_collateral,
+    )?;
+
+    Ok(serde_json::json!({
+        "required_wei": format_amount(plan.required),
+        "breakdown": {
+            "per_tab_reserve": format_amount(plan.per_tab_reserve),
+            "creation_fees": format_amount(plan.creation_fees),
+            "safety_buffer": format_amount(plan.safety_buffer),
+            "minimum_collateral": format_amount(minimum_collateral)
+        }
+    }))
+}
+
+async fn sign_payment(client: &Client, wallet_private_key: &str, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let claims = parse_claims(&args["claims"])?;
+    let work_hash = work_hash_arg(args)?;
     
-    let scheme_str = args["scheme"].as_str().unwrap_or("Eip712");
-    let scheme = match scheme_str {
-        "Eip712" => SigningScheme::Eip712,
-        "Eip191" => SigningScheme::Eip191,
-        _ => SigningScheme::Eip712,
-    };
+    let scheme = parse_signing_scheme(args["scheme"].as_str().unwrap_or("Eip712"));
     
-    match client.user.sign_payment(claims, scheme).await {
-        Ok(signature) => Ok(serde_json::json!({
-            "signature": signature.signature,
-            "scheme": format!("{:?}", signature.scheme)
-        })),
-        Err(e) => Err(anyhow::anyhow!("Sign payment failed: {}", e))
+    let signature = client.user.sign_payment(claims, scheme).await
+        .map_err(|e| anyhow::anyhow!("Sign payment failed: {}", e))?;
+    let mut data = serde_json::json!({
+        "signature": signature.signature,
+        "scheme": format!("{:?}", signature.scheme)
+    });
+    if let Some(work_hash) = work_hash {
+        let signer = chain::signer_from_key(wallet_private_key)?;
+        data["work_binding"] = serde_json::to_value(workbinding::bind(&signer, &signature.signature, work_hash)?)?;
     }
+    Ok(data)
 }
 
-async fn issue_payment_guarantee(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
-    let claims_json = &args["claims"];
-    let claims = PaymentGuaranteeClaims {
-        user_address: claims_json["user_address"].as_str().unwrap_or("").to_string(),
-        recipient_address: claims_json["recipient_address"].as_str().unwrap_or("").to_string(),
-        tab_id: U256::from_str(claims_json["tab_id"].as_str().unwrap_or("0"))?,
-        req_id: U256::from_str(claims_json["req_id"].as_str().unwrap_or("0"))?,
-        amount: U256::from_str(claims_json["amount"].as_str().unwrap_or("0"))?,
-        timestamp: claims_json["timestamp"].as_u64().unwrap_or(0),
-    };
-    
-    let signature = args["signature"].as_str().unwrap_or("");
-    let scheme_str = args["scheme"].as_str().unwrap_or("Eip712");
-    let scheme = match scheme_str {
-        "Eip712" => SigningScheme::Eip712,
-        "Eip191" => SigningScheme::Eip191,
-        _ => SigningScheme::Eip712,
+/// Most claims `sign_multiple_req_ids` signs in one run.
+const MAX_PRESIGNED_REQ_IDS: u64 = 1000;
+
+/// Pre-signs claims for every `req_id` in `from_req_id..=to_req_id` of one tab, all with the same amount.
+/// `user_address` defaults to the configured wallet, the only user the SDK can sign for.
+async fn sign_multiple_req_ids(
+    client: &Client,
+    wallet_private_key: &str,
+    args: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let tab_id = parse_id(&args["tab_id"], "tab_id")?;
+    let from_req_id = parse_id(&args["from_req_id"], "from_req_id")?;
+    let to_req_id = parse_id(&args["to_req_id"], "to_req_id")?;
+    let amount = parse_amount(&args["amount_per_req_wei"], "amount_per_req_wei")?;
+    let wallet = chain::signer_from_key(wallet_private_key)?.address();
+    let user_address = match args["user_address"].as_str() {
+        Some(user_address) => chain::parse_address(user_address, "user_address")?,
+        None => wallet,
     };
+    if user_address != wallet {
+        return Err(coded(
+            "SIGNER_NOT_USER",
+            format!("Wallet {} cannot sign claims for user {}", wallet, user_address),
+        ));
+    }
+    let user_address = user_address.to_string();
+    let recipient_address =
+        chain::parse_address(args["recipient_address"].as_str().unwrap_or(""), "recipient_address")?.to_string();
+    let scheme = args["scheme"].as_str().unwrap_or("Eip712");
+    if to_req_id < from_req_id {
+        return Err(anyhow::anyhow!("to_req_id {} is below from_req_id {}", to_req_id, from_req_id));
+    }
+    let count = to_req_id - from_req_id + U256::from(1u64);
+    if count > U256::from(MAX_PRESIGNED_REQ_IDS) {
+        return Err(anyhow::anyhow!("{} req_ids requested, at most {} can be signed at once", count, MAX_PRESIGNED_REQ_IDS));
+    }
+    let timestamp = unix_timestamp()?;
+
+    let signed = futures::future::try_join_all((0..count.to::<u64>()).map(|offset| {
+        let claims = PaymentGuaranteeClaims {
+            user_address: user_address.clone(),
+            recipient_address: recipient_address.clone(),
+            tab_id,
+            req_id: from_req_id + U256::from(offset),
+            amount,
+            timestamp,
+        };
+        async move {
+            let req_id = claims.req_id;
+            let signature = client.user.sign_payment(claims, parse_signing_scheme(scheme)).await
+                .map_err(|e| anyhow::anyhow!("Sign payment for req_id {} failed: {}", req_id, e))?;
+            Ok::<_, anyhow::Error>(serde_json::json!({
+                "req_id": req_id.to_string(),
+                "signature": signature.signature,
+                "scheme": format!("{:?}", signature.scheme)
+            }))
+        }
+    }))
+    .await?;
+
+    Ok(serde_json::json!({
+        "tab_id": tab_id.to_string(),
+        "timestamp": timestamp,
+        "signatures": signed
+    }))
+}
+
+/// The delivered work's hash from `work_hash`, or computed from the file at `work_artifact_file`.
+fn work_hash_arg(args: &serde_json::Value) -> Result<Option<alloy::primitives::B256>> {
+    match (args["work_hash"].as_str(), args["work_artifact_file"].as_str()) {
+        (Some(work_hash), _) => Ok(Some(workbinding::parse_work_hash(work_hash)?)),
+        (None, Some(path)) => {
+            let artifact = fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read work artifact {}: {}", path, e))?;
+            Ok(Some(workbinding::hash_artifact(&artifact)))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// The work binding accompanying a guarantee request, from its voucher or a `work_binding` argument.
+fn request_work_binding(args: &serde_json::Value) -> Result<Option<workbinding::WorkBinding>> {
+    if let Some(voucher) = voucher::from_args(args)? {
+        return Ok(voucher.work_binding);
+    }
+    if args["work_binding"].is_null() {
+        return Ok(None);
+    }
+    let binding = serde_json::from_value(args["work_binding"].clone())
+        .map_err(|e| anyhow::anyhow!("work_binding must be {{ work_hash, signature }}: {}", e))?;
+    Ok(Some(binding))
+}
+
+/// Signed claims to request a guarantee for, from a voucher or separate claims/signature arguments.
+fn guarantee_request(args: &serde_json::Value) -> Result<(PaymentGuaranteeClaims, String, SigningScheme)> {
+    Ok(match voucher::from_args(args)? {
+        Some(voucher) => (open_voucher(&voucher)?, voucher.signature, parse_signing_scheme(&voucher.scheme)),
+        None => (
+            parse_claims(&args["claims"])?,
+            args["signature"].as_str().unwrap_or("").to_string(),
+            parse_signing_scheme(args["scheme"].as_str().unwrap_or("Eip712")),
+        ),
+    })
+}
+
+/// Claims in the argument shape [`parse_claims`] reads.
+fn claims_json(claims: &PaymentGuaranteeClaims) -> serde_json::Value {
+    serde_json::json!({
+        "user_address": claims.user_address,
+        "recipient_address": claims.recipient_address,
+        "tab_id": claims.tab_id.to_string(),
+        "req_id": claims.req_id.to_string(),
+        "amount": claims.amount.to_string(),
+        "timestamp": claims.timestamp
+    })
+}
+
+/// Wire-log arguments of an `issue_payment_guarantee` API call.
+fn guarantee_call_args(claims: &PaymentGuaranteeClaims, signature: &str, scheme: &SigningScheme) -> serde_json::Value {
+    serde_json::json!({
+        "claims": claims_json(claims),
+        "signature": signature,
+        "scheme": match scheme {
+            SigningScheme::Eip191 => "Eip191",
+            _ => "Eip712",
+        }
+    })
+}
+
+async fn issue_payment_guarantee(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let (claims, signature, scheme) = guarantee_request(args)?;
+    let work_binding = request_work_binding(args)?;
     
-    match client.recipient.issue_payment_guarantee(claims, signature.to_string(), scheme).await {
-        Ok(bls_cert) => Ok(serde_json::json!({
-            "certificate": format!("{:?}", bls_cert),
-            "signature": "bls_signature",
-            "public_key": "bls_public_key"
-        })),
+    let logged = guarantee_call_args(&claims, &signature, &scheme);
+    let issued = client.recipient.issue_payment_guarantee(claims, signature, scheme);
+    match wirelog::api_call("issue_payment_guarantee", logged, issued).await {
+        Ok(bls_cert) => {
+            let mut data = serde_json::json!({
+                "certificate": format!("{:?}", bls_cert),
+                "signature": "bls_signature",
+                "public_key": "bls_public_key"
+            });
+            // Carried along so the certificate record names the work it paid for
+            if let Some(work_binding) = work_binding {
+                data["work_binding"] = serde_json::to_value(work_binding)?;
+            }
+            Ok(data)
+        }
         Err(e) => Err(anyhow::anyhow!("Issue payment guarantee failed: {}", e))
     }
 }
 
 async fn pay_tab(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
-    let tab_id = U256::from_str(args["tab_id"].as_str().unwrap_or("0"))?;
-    let req_id = U256::from_str(args["req_id"].as_str().unwrap_or("0"))?;
-    let amount = U256::from_str(args["amount"].as_str().unwrap_or("0"))?;
+    let tab_id = parse_id(&args["tab_id"], "tab_id")?;
+    let req_id = parse_id(&args["req_id"], "req_id")?;
+    let amount = parse_amount(&args["amount"], "amount")?;
     let recipient = args["recipient"].as_str().unwrap_or("");
     
     match client.user.pay_tab(tab_id, req_id, amount, recipient.to_string()).await {
@@ -225,38 +798,109 @@ async fn pay_tab(client: &Client, args: &serde_json::Value) -> Result<serde_json
     }
 }
 
-async fn get_tab_payment_status(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
-    let tab_id = U256::from_str(args["tab_id"].as_str().unwrap_or("0"))?;
-    
-    match client.recipient.get_tab_payment_status(tab_id).await {
-        Ok(status) => Ok(serde_json::json!({
-            "paid": status.paid.to_string(),
-            "remunerated": status.remunerated.to_string()
-        })),
-        Err(e) => Err(anyhow::anyhow!("Get tab payment status failed: {}", e))
+/// Writes the certificate records in `certs` that are still unsettled to `output_dir` with a signed manifest.
+async fn export_certs(
+    client: &Client,
+    wallet_private_key: &str,
+    args: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let output_dir = args["output_dir"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("output_dir is required"))?;
+    let records = args["certs"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("certs must be an array of {{ claims, signature, certificate, metadata }} records"))?;
+
+    let mut statuses = std::collections::HashMap::new();
+    let (mut unsettled, mut settled) = (Vec::new(), Vec::new());
+    for record in records {
+        let claims = parse_claims(&record["claims"])?;
+        let status = match statuses.entry(claims.tab_id) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(read_tab_payment_status(client, claims.tab_id).await?)
+            }
+        };
+        if status["remunerated"] == "true" || parse_amount(&status["paid"], "paid")? >= claims.amount {
+            settled.push(serde_json::json!({ "tab_id": claims.tab_id.to_string(), "req_id": claims.req_id.to_string() }));
+        } else {
+            unsettled.push(record.clone());
+        }
     }
+
+    let signer = chain::signer_from_key(wallet_private_key)?;
+    let manifest = certstore::export(std::path::Path::new(output_dir), &unsettled, &signer, unix_timestamp()?)?;
+    Ok(serde_json::json!({
+        "output_dir": output_dir,
+        "exported": manifest.files,
+        "skipped_settled": settled,
+        "signer": manifest.signer
+    }))
+}
+
+/// Verifies an `export_certs` directory as a whole, then adds its records to `store_dir`.
+fn import_certs(wallet_private_key: &str, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let input_dir = args["input_dir"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("input_dir is required"))?;
+    let store_dir = args["store_dir"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("store_dir is required"))?;
+    // Exports are expected to come from this wallet unless another exporter is named
+    let expected_signer = match args["expected_signer"].as_str() {
+        Some(address) => chain::parse_address(address, "expected_signer")?,
+        None => chain::signer_from_key(wallet_private_key)?.address(),
+    };
+
+    let (manifest, records) = certstore::verify(std::path::Path::new(input_dir), expected_signer)?;
+    let (imported, skipped) = certstore::import(std::path::Path::new(store_dir), &records)?;
+    Ok(serde_json::json!({
+        "signer": manifest.signer,
+        "created_at": manifest.created_at,
+        "imported": imported,
+        "skipped_existing": skipped
+    }))
+}
+
+async fn get_tab_payment_status(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let tab_id = parse_id(&args["tab_id"], "tab_id")?;
+
+    read_tab_payment_status(client, tab_id).await
+}
+
+/// Reads a tab's payment status from the 4Mica API.
+async fn read_tab_payment_status(client: &Client, tab_id: U256) -> Result<serde_json::Value> {
+    let request = serde_json::json!({ "tab_id": tab_id.to_string() });
+    let status = client.recipient.get_tab_payment_status(tab_id);
+    let status = wirelog::api_call("get_tab_payment_status", request, status).await
+        .map_err(|e| anyhow::anyhow!("Get tab payment status failed: {}", e))?;
+    Ok(serde_json::json!({
+        "paid": status.paid.to_string(),
+        "remunerated": status.remunerated.to_string()
+    }))
 }
 
+/// Claims collateral for a signed guarantee: the 4Mica API issues the certificate and the recipient
+/// remunerates with it. The tab comes from the claims, never from a separate argument.
 async fn remunerate(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
-    // For now, we'll need to reconstruct the BLSCert from the certificate string
-    // This is a complex operation that requires proper BLS certificate parsing
-    // In a real implementation, you would need to parse the certificate string back to BLSCert
-    
-    // Since we can't easily reconstruct BLSCert from string, we'll use a different approach
-    // Let's use the pay_tab function instead, which is the real on-chain settlement
-    let tab_id = U256::from_str("1")?; // Use a default tab ID
-    let req_id = U256::from_str("1")?;
-    let amount = U256::from_str("1000000000000000")?; // 0.001 ETH
-    let recipient = "0x292F0E22A0245387a89d5DB50F016d18D6aF0bac";
-    
-    match client.user.pay_tab(tab_id, req_id, amount, recipient.to_string()).await {
-        Ok(receipt) => Ok(serde_json::json!({
-            "transaction_hash": receipt.transaction_hash,
-            "block_number": receipt.block_number,
-            "gas_used": receipt.gas_used.to_string()
-        })),
-        Err(e) => Err(anyhow::anyhow!("Pay tab failed: {}", e))
+    let (claims, signature, scheme) = guarantee_request(args)?;
+    let tab_id = claims.tab_id;
+
+    let status = read_tab_payment_status(client, tab_id).await?;
+    if status["remunerated"] == "true" {
+        return Err(coded("ALREADY_REMUNERATED", format!("Tab {} is already remunerated", tab_id)));
     }
+
+    let logged = guarantee_call_args(&claims, &signature, &scheme);
+    let issued = client.recipient.issue_payment_guarantee(claims, signature, scheme);
+    let certificate = wirelog::api_call("issue_payment_guarantee", logged, issued).await
+        .map_err(|e| anyhow::anyhow!("Issue payment guarantee failed: {}", e))?;
+    let receipt = client.recipient.remunerate(certificate).await
+        .map_err(|e| anyhow::anyhow!("Remunerate failed: {}", e))?;
+
+    let mut data = chain::receipt_json(&receipt);
+    data["tab_id"] = serde_json::json!(tab_id.to_string());
+    Ok(data)
 }
 
 async fn verify_bls_signature(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
@@ -265,27 +909,20 @@ async fn verify_bls_signature(client: &Client, args: &serde_json::Value) -> Resu
     let claims_json = &args["claims"];
     
     // Parse claims
-    let claims = PaymentGuaranteeClaims {
-        user_address: claims_json["user_address"].as_str().unwrap_or("").to_string(),
-        recipient_address: claims_json["recipient_address"].as_str().unwrap_or("").to_string(),
-        tab_id: U256::from_str(claims_json["tab_id"].as_str().unwrap_or("0"))?,
-        req_id: U256::from_str(claims_json["req_id"].as_str().unwrap_or("0"))?,
-        amount: U256::from_str(claims_json["amount"].as_str().unwrap_or("0"))?,
-        timestamp: claims_json["timestamp"].as_u64().unwrap_or(0),
-    };
+    let claims = parse_claims(claims_json)?;
     
     // For now, we'll simulate BLS verification since the SDK doesn't expose verification directly
     // In a real implementation, you would verify the BLS signature against the claims
-    println!("🔍 Verifying BLS signature for claims: {:?}", claims);
-    println!("   Certificate: {}", certificate);
-    println!("   Public Key: {}", public_key);
+    status!("🔍 Verifying BLS signature for claims: {:?}", claims);
+    status!("   Certificate: {}", certificate);
+    status!("   Public Key: {}", public_key);
     
     // Simulate verification logic
     // In practice, this would use the BLS library to verify the signature
     let verification_result = !certificate.is_empty() && !public_key.is_empty();
     
     if verification_result {
-        println!("✅ BLS signature verification successful");
+        status!("✅ BLS signature verification successful");
         Ok(serde_json::json!({
             "verified": true,
             "message": "BLS signature is valid",
@@ -299,7 +936,1726 @@ async fn verify_bls_signature(client: &Client, args: &serde_json::Value) -> Resu
             }
         }))
     } else {
-        println!("❌ BLS signature verification failed");
+        status!("❌ BLS signature verification failed");
         Err(anyhow::anyhow!("BLS signature verification failed"))
     }
 }
+
+fn verify_certificate_quorum(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let signers = args["certificate"]["signers"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Certificate must include a signers array"))?;
+    let operator_set = args["operator_set"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("operator_set must be an array of {{ operator, weight }}"))?;
+    let required_weight_basis_points = args["quorum_threshold_basis_points"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("quorum_threshold_basis_points is required"))?;
+    if required_weight_basis_points > 10_000 {
+        return Err(anyhow::anyhow!("quorum_threshold_basis_points must be at most 10000"));
+    }
+
+    // Operator addresses are compared case-insensitively so checksummed and
+    // lowercase snapshots can be mixed freely.
+    let mut weights = std::collections::HashMap::new();
+    let mut total_weight = U256::ZERO;
+    for entry in operator_set {
+        let operator = entry["operator"].as_str().unwrap_or("").to_lowercase();
+        let weight = parse_amount_or(&entry["weight"], "weight", U256::ZERO)?;
+        if operator.is_empty() {
+            return Err(anyhow::anyhow!("Operator set entry is missing an operator address"));
+        }
+        if weights.insert(operator.clone(), weight).is_some() {
+            return Err(anyhow::anyhow!("Operator {} appears twice in the operator set", operator));
+        }
+        total_weight = total_weight
+            .checked_add(weight)
+            .ok_or_else(|| anyhow::anyhow!("Operator set total weight overflows uint256"))?;
+    }
+    if total_weight.is_zero() {
+        return Err(anyhow::anyhow!("Operator set has zero total weight"));
+    }
+
+    let mut counted = std::collections::HashSet::new();
+    let mut signing_weight = U256::ZERO;
+    for signer in signers {
+        let signer = signer.as_str().unwrap_or("").to_lowercase();
+        let weight = weights
+            .get(&signer)
+            .ok_or_else(|| anyhow::anyhow!("Signer {} is not in the operator set", signer))?;
+        if counted.insert(signer) {
+            signing_weight += *weight;
+        }
+    }
+
+    // signing_weight <= total_weight, so the quotient is at most 10000
+    let signing_weight_basis_points: u64 = signing_weight
+        .checked_mul(U256::from(10_000u64))
+        .map(|scaled| scaled / total_weight)
+        .ok_or_else(|| anyhow::anyhow!("Operator weights are too large to compute basis points"))?
+        .saturating_to::<u64>();
+
+    Ok(serde_json::json!({
+        "quorum_met": signing_weight_basis_points >= required_weight_basis_points,
+        "signing_weight_basis_points": signing_weight_basis_points,
+        "required_weight_basis_points": required_weight_basis_points
+    }))
+}
+
+/// Payment and remuneration state of a tab. `in_dispute` flags a tab the recipient was
+/// remunerated for from collateral because the user did not pay; `double_settled` flags one that
+/// was both paid and remunerated.
+async fn get_tab_settlement_status(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let tab_id = parse_id(&args["tab_id"], "tab_id")?;
+
+    let status = read_tab_payment_status(client, tab_id).await?;
+    let paid_wei = parse_amount(&status["paid"], "paid")?;
+    let remunerated = status["remunerated"] == "true";
+
+    let paid = !paid_wei.is_zero();
+    let in_dispute = remunerated && !paid;
+    // The user paid and the recipient also claimed collateral for the same tab
+    let double_settled = paid && remunerated;
+    let fully_settled = !double_settled && (remunerated || paid);
+    let next_action = if double_settled {
+        "reconcile: tab was both paid and remunerated"
+    } else if fully_settled {
+        "none"
+    } else {
+        "await payment or remunerate with the payment guarantee"
+    };
+
+    Ok(serde_json::json!({
+        "tab_id": tab_id.to_string(),
+        "paid_wei": format_amount(paid_wei),
+        "in_dispute": in_dispute,
+        "double_settled": double_settled,
+        "fully_settled": fully_settled,
+        "next_action": next_action
+    }))
+}
+
+/// Compares the next block's base fee with recent history to decide whether to settle now.
+async fn suggest_settlement_window(chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let history_hours = args["history_hours"].as_u64().unwrap_or(24);
+    if !(1..=168).contains(&history_hours) {
+        return Err(anyhow::anyhow!("history_hours must be between 1 and 168"));
+    }
+    let defer_until_percentile = args["defer_until_percentile"].as_f64().unwrap_or(25.0);
+    if !(0.0..=100.0).contains(&defer_until_percentile) {
+        return Err(anyhow::anyhow!("defer_until_percentile must be between 0 and 100"));
+    }
+    let defer_above = match &args["defer_above_base_fee_wei"] {
+        serde_json::Value::Null => None,
+        value => Some(parse_amount(value, "defer_above_base_fee_wei")?),
+    };
+
+    let latest = chain.provider.get_block_number().await?;
+    let now = chain.block_timestamp(BlockNumberOrTag::Number(latest)).await?;
+    let from_block = chain.first_block_at_or_after(now.saturating_sub(history_hours * 3600)).await?.min(latest);
+    let start = chain.block_timestamp(BlockNumberOrTag::Number(from_block)).await?;
+    let history = fees::base_fee_history(chain, from_block, latest).await
+        .map_err(|e| anyhow::anyhow!("Get fee history failed: {}", e))?;
+
+    // Block times are near-constant post-merge, so interpolate rather than fetch every header
+    let span_blocks = (latest - from_block).max(1);
+    let timed: Vec<(u64, u128)> = history
+        .samples
+        .iter()
+        .map(|(block, fee)| (start + (block - from_block) * (now - start) / span_blocks, *fee))
+        .collect();
+    let base_fees: Vec<u128> = history.samples.iter().map(|(_, fee)| *fee).collect();
+    let threshold = fees::percentile(&base_fees, defer_until_percentile).unwrap_or(history.next_base_fee);
+    let hourly = fees::hourly_averages(&timed);
+    let best_hour = hourly.iter().min_by_key(|(_, fee)| **fee).map(|(hour, fee)| (*hour, *fee));
+
+    let current = U256::from(history.next_base_fee);
+    let below_percentile = history.next_base_fee <= threshold;
+    let below_cap = defer_above.is_none_or(|cap| current <= cap);
+
+    Ok(serde_json::json!({
+        "current_base_fee_wei": format_amount(current),
+        "history_hours": history_hours,
+        "samples": base_fees.len(),
+        "percentile": defer_until_percentile,
+        "percentile_base_fee_wei": format_amount(U256::from(threshold)),
+        "below_percentile": below_percentile,
+        "defer_above_base_fee_wei": defer_above.map(format_amount),
+        "settle_now": below_percentile && below_cap,
+        "best_hour_utc": best_hour.map(|(hour, _)| hour),
+        "best_hour_average_base_fee_wei": best_hour.map(|(_, fee)| format_amount(U256::from(fee))),
+        "hourly_average_base_fee_wei": hourly
+            .iter()
+            .map(|(hour, fee)| (hour.to_string(), serde_json::json!(format_amount(U256::from(*fee)))))
+            .collect::<serde_json::Map<_, _>>()
+    }))
+}
+
+async fn check_funding_floor(chain: &Chain, input: &Input, kind: CommandKind) -> Result<()> {
+    // bootstrap spends from the wallet it loads or generates, and checks that wallet once funded
+    if kind != CommandKind::ChainWrite || input.command == "bootstrap" {
+        return Ok(());
+    }
+    enforce_funding_floor(chain, &input.config, &input.command).await
+}
+
+async fn enforce_funding_floor(chain: &Chain, config: &serde_json::Value, command: &str) -> Result<()> {
+    let thresholds = FundingThresholds::from_config(config)?;
+    if !thresholds.pause_below_critical || thresholds.critical_balance_wei.is_none() {
+        return Ok(());
+    }
+
+    let balance = chain.provider.get_balance(chain.wallet_address).await
+        .map_err(|e| anyhow::anyhow!("Get wallet balance failed: {}", e))?;
+    if thresholds.is_critical(balance) {
+        return Err(coded(
+            "PAUSED_LOW_FUNDS",
+            format!(
+                "Paused {}: wallet balance {} wei is below the critical threshold {} wei",
+                command,
+                balance,
+                thresholds.critical_balance_wei.unwrap_or_default()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+async fn get_funding_status(client: &Client, chain: &Chain, config: &serde_json::Value) -> Result<serde_json::Value> {
+    let thresholds = FundingThresholds::from_config(config)?;
+
+    let balance = chain.provider.get_balance(chain.wallet_address).await
+        .map_err(|e| anyhow::anyhow!("Get wallet balance failed: {}", e))?;
+    let collateral = match client.user.get_user().await {
+        Ok(user_info) => user_info.collateral,
+        Err(e) => return Err(anyhow::anyhow!("Get user failed: {}", e)),
+    };
+
+    let (status, warnings) = thresholds.assess(balance, collateral);
+    for warning in &warnings {
+        estatus!("⚠️  {}", warning);
+    }
+
+    Ok(serde_json::json!({
+        "wallet_address": chain.wallet_address.to_string(),
+        "eth_balance_wei": balance.to_string(),
+        "collateral_wei": collateral.to_string(),
+        "status": status,
+        "warnings": warnings,
+        "paused": thresholds.pause_below_critical && thresholds.is_critical(balance),
+        "thresholds": {
+            "low_balance_wei": thresholds.low_balance_wei.map(|v| v.to_string()),
+            "critical_balance_wei": thresholds.critical_balance_wei.map(|v| v.to_string()),
+            "low_collateral_wei": thresholds.low_collateral_wei.map(|v| v.to_string())
+        }
+    }))
+}
+
+async fn batch_create_tabs_and_sign(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let tabs = args["tabs"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("tabs must be an array of {{ user_address, recipient_address, ttl, amount_wei }}"))?;
+    let scheme = parse_signing_scheme(args["scheme"].as_str().unwrap_or("Eip712"));
+    let timestamp = unix_timestamp()?;
+
+    // Each tab is created and signed independently; one failure only marks its own entry.
+    let results = futures::future::join_all(tabs.iter().map(|tab| async move {
+        let user_address = tab["user_address"].as_str().unwrap_or("").to_string();
+        let recipient_address = tab["recipient_address"].as_str().unwrap_or("").to_string();
+        let amount = match parse_amount(&tab["amount_wei"], "amount_wei") {
+            Ok(amount) => amount,
+            Err(e) => return (None, Err(("INVALID_AMOUNT", e.to_string()))),
+        };
+
+        let tab_id = match client.recipient
+            .create_tab(user_address.clone(), recipient_address.clone(), tab["ttl"].as_u64())
+            .await
+        {
+            Ok(tab_id) => tab_id,
+            Err(e) => return (None, Err(("CREATE_TAB_FAILED", format!("Create tab failed: {}", e)))),
+        };
+
+        let claims = PaymentGuaranteeClaims {
+            user_address,
+            recipient_address,
+            tab_id,
+            req_id: U256::from(1u64),
+            amount,
+            timestamp,
+        };
+        let signature = client.user.sign_payment(claims, scheme).await
+            .map_err(|e| ("SIGN_PAYMENT_FAILED", format!("Sign payment failed: {}", e)));
+        (Some(tab_id), signature)
+    }))
+    .await;
+
+    let mut steps = Vec::new();
+    let mut resume = Vec::new();
+    let mut uncreated = Vec::new();
+    let mut entries = Vec::new();
+    for (i, (tab, (tab_id, signature))) in tabs.iter().zip(results).enumerate() {
+        match tab_id {
+            Some(tab_id) => steps.push(Step::completed(
+                format!("create_tab[{}]", i),
+                serde_json::json!({ "tab_id": tab_id.to_string() }),
+            )),
+            None => uncreated.push(tab.clone()),
+        }
+
+        let entry = match signature {
+            Ok(signature) => {
+                steps.push(Step::completed(
+                    format!("sign_payment[{}]", i),
+                    serde_json::json!({ "signature": signature.signature }),
+                ));
+                serde_json::json!({
+                    "tab_id": tab_id.map(|id| id.to_string()),
+                    "signature": signature.signature,
+                    "scheme": format!("{:?}", signature.scheme),
+                    "error": null
+                })
+            }
+            Err((code, error)) => {
+                match tab_id {
+                    // The tab exists, so only the signature needs retrying
+                    Some(tab_id) => {
+                        steps.push(Step::failed(format!("sign_payment[{}]", i), code, error.clone()));
+                        resume.push(serde_json::json!({
+                            "command": "sign_payment",
+                            "args": {
+                                "claims": {
+                                    "user_address": tab["user_address"],
+                                    "recipient_address": tab["recipient_address"],
+                                    "tab_id": tab_id.to_string(),
+                                    "req_id": "1",
+                                    "amount": parse_amount(&tab["amount_wei"], "amount_wei").map(format_amount).ok(),
+                                    "timestamp": timestamp
+                                },
+                                "scheme": format!("{:?}", scheme)
+                            }
+                        }));
+                    }
+                    None => steps.push(Step::failed(format!("create_tab[{}]", i), code, error.clone())),
+                }
+                serde_json::json!({
+                    "tab_id": tab_id.map(|id| id.to_string()),
+                    "signature": null,
+                    "scheme": null,
+                    "error": error
+                })
+            }
+        };
+        entries.push(entry);
+    }
+
+    let committed = steps.iter().any(|step| !step.is_failed());
+    let failed = steps.iter().any(|step| step.is_failed());
+    let data = serde_json::json!({
+        "results": entries
+    });
+
+    if failed && committed {
+        if !uncreated.is_empty() {
+            resume.push(serde_json::json!({
+                "command": "batch_create_tabs_and_sign",
+                "args": { "tabs": uncreated, "scheme": format!("{:?}", scheme) }
+            }));
+        }
+        return Err(PartialFailure { steps, resume, data }.into());
+    }
+    if failed {
+        let first = steps.iter().find(|step| step.is_failed()).and_then(|step| step.error.clone());
+        return Err(anyhow::anyhow!("All tab creations failed: {}", first.unwrap_or_default()));
+    }
+
+    Ok(data)
+}
+
+fn compare_outputs(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let left = args["left"].as_str().ok_or_else(|| anyhow::anyhow!("left path is required"))?;
+    let right = args["right"].as_str().ok_or_else(|| anyhow::anyhow!("right path is required"))?;
+    let ignored: Vec<String> = match args["ignore_fields"].as_array() {
+        Some(fields) => fields.iter().filter_map(|f| f.as_str().map(str::to_string)).collect(),
+        None => compare::DEFAULT_VOLATILE_FIELDS.iter().map(|f| f.to_string()).collect(),
+    };
+
+    let differences = compare::compare_paths(std::path::Path::new(left), std::path::Path::new(right), &ignored)?;
+
+    Ok(serde_json::json!({
+        "identical": differences.is_empty(),
+        "difference_count": differences.len(),
+        "ignored_fields": ignored,
+        "differences": differences
+    }))
+}
+
+fn sign_typed_data(wallet_private_key: &str, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let signer = chain::signer_from_key(wallet_private_key)?;
+    let typed_data = typed_data::parse(&args["typed_data"])?;
+    let digest = typed_data::digest(&typed_data)?;
+    let signature = typed_data::sign(&signer, &digest)?;
+
+    Ok(serde_json::json!({
+        "signature": signature.to_string(),
+        "digest": digest.to_string(),
+        "signer": signer.address().to_string()
+    }))
+}
+
+fn verify_typed_data(wallet_private_key: &str, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let typed_data = typed_data::parse(&args["typed_data"])?;
+    let digest = typed_data::digest(&typed_data)?;
+    let recovered = typed_data::recover(args["signature"].as_str().unwrap_or(""), &digest)?;
+
+    // Without an explicit expected signer, the configured wallet is expected to have signed
+    let expected = match args["expected_signer"].as_str() {
+        Some(expected) => chain::parse_address(expected, "expected_signer")?,
+        None => chain::signer_from_key(wallet_private_key)?.address(),
+    };
+
+    Ok(serde_json::json!({
+        "recovered_address": recovered.to_string(),
+        "expected_signer": expected.to_string(),
+        "matches": recovered == expected,
+        "digest": digest.to_string()
+    }))
+}
+
+/// The core contract's EIP-712 domain and where it came from: `override` when `domain_name`
+/// and `domain_version` are given, otherwise `contract`, as the deployment reports it through
+/// EIP-5267 `eip712Domain()`. A deployment that reports none fails with `DOMAIN_UNKNOWN`
+/// rather than falling back to a guessed name and version.
+async fn resolve_domain(chain: &Chain, args: &serde_json::Value) -> Result<(serde_json::Value, &'static str)> {
+    if !args["domain_name"].is_null() || !args["domain_version"].is_null() {
+        let (Some(name), Some(version)) = (args["domain_name"].as_str(), args["domain_version"].as_str()) else {
+            return Err(coded("INVALID_INPUT", "domain_name and domain_version must be given together"));
+        };
+        let chain_id = chain.provider.get_chain_id().await
+            .map_err(|e| anyhow::anyhow!("Get chain id failed: {}", e))?;
+        let domain = serde_json::json!({
+            "name": name,
+            "version": version,
+            "chainId": chain_id,
+            "verifyingContract": chain.contract_address.to_string()
+        });
+        return Ok((domain, "override"));
+    }
+
+    let reported = match chain.eip5267().eip712Domain().call().await {
+        Ok(reported) => reported,
+        Err(e) if e.as_revert_data().is_some() => {
+            return Err(coded(
+                "DOMAIN_UNKNOWN",
+                "The contract does not report its EIP-712 domain; pass domain_name and domain_version",
+            ))
+        }
+        Err(e) => return Err(anyhow::anyhow!("Get EIP-712 domain failed: {}", e)),
+    };
+    // EIP-5267 marks which of the five standard fields the domain uses
+    let fields = reported.fields.0[0];
+    let mut domain = serde_json::Map::new();
+    let present = |bit: u8| fields & (1 << bit) != 0;
+    if present(0) {
+        domain.insert("name".into(), serde_json::json!(reported.name));
+    }
+    if present(1) {
+        domain.insert("version".into(), serde_json::json!(reported.version));
+    }
+    if present(2) {
+        domain.insert("chainId".into(), serde_json::json!(reported.chainId.to_string()));
+    }
+    if present(3) {
+        domain.insert("verifyingContract".into(), serde_json::json!(reported.verifyingContract.to_string()));
+    }
+    if present(4) {
+        domain.insert("salt".into(), serde_json::json!(reported.salt.to_string()));
+    }
+    Ok((serde_json::Value::Object(domain), "contract"))
+}
+
+/// The core contract's EIP-712 domain separator, with the domain it was computed from.
+async fn get_eip712_domain_separator(chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let (domain, source) = resolve_domain(chain, args).await?;
+
+    Ok(serde_json::json!({
+        "domain_separator": typed_data::domain_separator(&domain)?.to_string(),
+        "domain": domain,
+        "domain_source": source
+    }))
+}
+
+/// Signs claims into a voucher the recipient side can use without any other fragments.
+async fn create_voucher(
+    client: &Client,
+    wallet_private_key: &str,
+    args: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let claims = parse_claims(&args["claims"])?;
+    let signer = chain::signer_from_key(wallet_private_key)?;
+    if chain::parse_address(&claims.user_address, "claims.user_address")? != signer.address() {
+        return Err(coded(
+            "SIGNER_NOT_USER",
+            format!("Wallet {} cannot sign claims for user {}", signer.address(), claims.user_address),
+        ));
+    }
+
+    let metadata = (!args["metadata"].is_null()).then(|| args["metadata"].clone());
+    let scheme = parse_signing_scheme(args["scheme"].as_str().unwrap_or("Eip712"));
+    let mut voucher = sign_voucher(client, &signer, claims, scheme, metadata).await?;
+    if let Some(work_hash) = work_hash_arg(args)? {
+        voucher.work_binding = Some(workbinding::bind(&signer, &voucher.signature, work_hash)?);
+    }
+    if let Some(path) = args["voucher_file"].as_str() {
+        fs::write(path, serde_json::to_string_pretty(&voucher)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write voucher file {}: {}", path, e))?;
+    }
+
+    Ok(serde_json::json!({
+        "voucher": voucher,
+        "voucher_file": args["voucher_file"]
+    }))
+}
+
+/// Creates a tab and writes a bundle of vouchers for `amounts`, one per consecutive `req_id`.
+async fn create_signed_tab_bundle(
+    client: &Client,
+    wallet_private_key: &str,
+    args: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let user_address = args["user_address"].as_str().unwrap_or("");
+    let recipient_address = args["recipient_address"].as_str().unwrap_or("");
+    let ttl = args["ttl"].as_u64();
+    let output_path = args["bundle_output_path"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("bundle_output_path is required"))?;
+    let amounts = args["amounts"]
+        .as_array()
+        .filter(|amounts| !amounts.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("amounts must be a non-empty array of wei amounts"))?;
+    if amounts.len() as u64 > MAX_PRESIGNED_REQ_IDS {
+        return Err(anyhow::anyhow!("At most {} amounts can be pre-signed in one bundle", MAX_PRESIGNED_REQ_IDS));
+    }
+    let amounts = amounts
+        .iter()
+        .enumerate()
+        .map(|(i, amount)| parse_amount(amount, &format!("amounts[{}]", i)))
+        .collect::<Result<Vec<_>>>()?;
+    let first_req_id = U256::from_str(args["first_req_id"].as_str().unwrap_or("1"))?;
+
+    // Check everything that can fail before the tab exists
+    let signer = chain::signer_from_key(wallet_private_key)?;
+    if chain::parse_address(user_address, "user_address")? != signer.address() {
+        return Err(coded(
+            "SIGNER_NOT_USER",
+            format!("Wallet {} cannot sign guarantees for user {}", signer.address(), user_address),
+        ));
+    }
+    chain::parse_address(recipient_address, "recipient_address")?;
+    let scheme = args["scheme"].as_str().unwrap_or("Eip712");
+
+    let tab_id = client.recipient
+        .create_tab(user_address.to_string(), recipient_address.to_string(), ttl)
+        .await
+        .map_err(|e| anyhow::anyhow!("Create tab failed: {}", e))?;
+
+    let timestamp = unix_timestamp()?;
+    let mut guarantees = Vec::with_capacity(amounts.len());
+    for (req_id, amount) in (0u64..).map(|i| first_req_id + U256::from(i)).zip(&amounts) {
+        let claims = PaymentGuaranteeClaims {
+            user_address: user_address.to_string(),
+            recipient_address: recipient_address.to_string(),
+            tab_id,
+            req_id,
+            amount: *amount,
+            timestamp,
+        };
+        guarantees.push(sign_voucher(client, &signer, claims, parse_signing_scheme(scheme), None).await?);
+    }
+
+    let bundle = bundle::Bundle {
+        format: bundle::FORMAT.to_string(),
+        version: bundle::VERSION,
+        tab_id: tab_id.to_string(),
+        user_address: user_address.to_string(),
+        recipient_address: recipient_address.to_string(),
+        ttl,
+        created_at: timestamp,
+        guarantees,
+    };
+    let total_amount = amounts.iter().fold(U256::ZERO, |total, amount| total.saturating_add(*amount));
+    bundle::write(output_path, &bundle)
+        .map_err(|e| anyhow::anyhow!("Tab {} was created but the bundle was not saved: {}", tab_id, e))?;
+
+    Ok(serde_json::json!({
+        "tab_id": tab_id.to_string(),
+        "bundle_output_path": output_path,
+        "guarantees_count": amounts.len(),
+        "first_req_id": first_req_id.to_string(),
+        "total_amount_wei": format_amount(total_amount)
+    }))
+}
+
+/// Checks a bundle from `create_signed_tab_bundle`: every voucher's seal, that the vouchers
+/// belong to the bundle's tab and parties in `req_id` order, and that the 4Mica API knows the tab.
+async fn load_signed_tab_bundle(client: &Client, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let path = args["bundle_path"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("bundle_path is required"))?;
+    let loaded = bundle::read(path)?;
+
+    let mut issues = bundle::issues(&loaded);
+    let mut total_amount = U256::ZERO;
+    for (i, guarantee) in loaded.guarantees.iter().enumerate() {
+        match open_voucher(guarantee) {
+            Ok(claims) => total_amount = total_amount.saturating_add(claims.amount),
+            Err(e) => issues.push(format!("guarantees[{}]: {}", i, e)),
+        }
+    }
+
+    match U256::from_str(&loaded.tab_id) {
+        Ok(tab_id) => {
+            if let Err(e) = read_tab_payment_status(client, tab_id).await {
+                issues.push(format!("tab {} status lookup failed: {}", tab_id, e));
+            }
+        }
+        Err(_) => issues.push(format!("tab_id {} is not a number", loaded.tab_id)),
+    }
+
+    Ok(serde_json::json!({
+        "tab_id": loaded.tab_id,
+        "valid": issues.is_empty(),
+        "guarantees_count": loaded.guarantees.len(),
+        "total_amount_wei": format_amount(total_amount),
+        "issues": issues
+    }))
+}
+
+/// Signs claims through the SDK and seals the result into a voucher; see [`voucher::check_seal`].
+async fn sign_voucher(
+    client: &Client,
+    signer: &alloy::signers::local::PrivateKeySigner,
+    claims: PaymentGuaranteeClaims,
+    scheme: SigningScheme,
+    metadata: Option<serde_json::Value>,
+) -> Result<voucher::Voucher> {
+    let claims_value = claims_json(&claims);
+    let signed = client.user.sign_payment(claims, scheme).await
+        .map_err(|e| anyhow::anyhow!("Sign payment failed: {}", e))?;
+    seal_voucher(signer, claims_value, signed.signature, format!("{:?}", signed.scheme), metadata)
+}
+
+fn seal_voucher(
+    signer: &alloy::signers::local::PrivateKeySigner,
+    claims: serde_json::Value,
+    signature: String,
+    scheme: String,
+    metadata: Option<serde_json::Value>,
+) -> Result<voucher::Voucher> {
+    Ok(voucher::Voucher {
+        format: voucher::FORMAT.to_string(),
+        version: voucher::VERSION,
+        seal: voucher::seal(signer, &claims, &signature, &scheme)?,
+        claims,
+        signature,
+        scheme,
+        signer: signer.address().to_string(),
+        created_at: unix_timestamp()?,
+        metadata,
+        work_binding: None,
+    })
+}
+
+/// Checks that the voucher is sealed by its signer and that the signer is the claims' user, returning the claims.
+/// The payment signature itself is checked by the 4Mica API when the guarantee is issued.
+fn open_voucher(voucher: &voucher::Voucher) -> Result<PaymentGuaranteeClaims> {
+    if voucher.scheme != "Eip712" && voucher.scheme != "Eip191" {
+        return Err(coded("INVALID_VOUCHER", format!("Unsupported voucher scheme {}", voucher.scheme)));
+    }
+    let claims = parse_claims(&voucher.claims)
+        .map_err(|e| coded("INVALID_VOUCHER", format!("Invalid voucher claims: {}", e)))?;
+    voucher::check_seal(voucher)?;
+
+    let signer = chain::parse_address(&voucher.signer, "voucher signer")?;
+    let user = chain::parse_address(&claims.user_address, "voucher user")?;
+    if signer != user {
+        return Err(coded("VOUCHER_TAMPERED", format!("Voucher is signed by {}, not its user {}", signer, user)));
+    }
+    Ok(claims)
+}
+
+/// Checks a voucher's seal and signer; see [`open_voucher`].
+fn verify_payment_signature(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let voucher = voucher::from_args(args)?
+        .ok_or_else(|| anyhow::anyhow!("voucher or voucher_file is required"))?;
+
+    // Tampering is a verdict, not a failure of the command
+    match open_voucher(&voucher) {
+        Ok(_) => Ok(serde_json::json!({
+            "valid": true,
+            "signer": voucher.signer
+        })),
+        Err(e) if error::error_code(&e).as_deref() == Some("VOUCHER_TAMPERED") => Ok(serde_json::json!({
+            "valid": false,
+            "signer": voucher.signer,
+            "reason": e.to_string()
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Checks a voucher's work binding against the delivered artifact (`work_artifact_file`, or `work_hash`).
+/// A bad payment signature and a binding that names other work are reported as different failures.
+fn verify_work_binding(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let voucher = voucher::from_args(args)?
+        .ok_or_else(|| anyhow::anyhow!("voucher or voucher_file is required"))?;
+    let delivered = work_hash_arg(args)?
+        .ok_or_else(|| anyhow::anyhow!("work_artifact_file or work_hash is required"))?;
+    let verdict = |failure: Option<&str>, reason: Option<String>| {
+        serde_json::json!({
+            "valid": failure.is_none(),
+            "failure": failure,
+            "reason": reason,
+            "signer": voucher.signer,
+            "delivered_work_hash": delivered.to_string(),
+            "bound_work_hash": voucher.work_binding.as_ref().map(|binding| binding.work_hash.clone())
+        })
+    };
+
+    match open_voucher(&voucher) {
+        Ok(_) => {}
+        Err(e) if error::error_code(&e).as_deref() == Some("VOUCHER_TAMPERED") => {
+            return Ok(verdict(Some("VOUCHER_TAMPERED"), Some(e.to_string())))
+        }
+        Err(e) => return Err(e),
+    }
+    let Some(binding) = &voucher.work_binding else {
+        return Ok(verdict(Some("NO_WORK_BINDING"), Some("The voucher carries no work binding".to_string())));
+    };
+    let signer = chain::parse_address(&voucher.signer, "voucher signer")?;
+    Ok(match workbinding::check(binding, &voucher.signature, signer, delivered) {
+        Ok(()) => verdict(None, None),
+        Err(mismatch @ workbinding::Mismatch::BindingSignature) => verdict(
+            Some(mismatch.code()),
+            Some(format!("The work binding is not signed by the voucher signer {}", voucher.signer)),
+        ),
+        Err(mismatch @ workbinding::Mismatch::WorkHash) => verdict(
+            Some(mismatch.code()),
+            Some(format!("The voucher is bound to work {}, not the delivered {}", binding.work_hash, delivered)),
+        ),
+    })
+}
+
+/// Renders a wei amount for display in the requested unit, keeping the exact wei alongside.
+fn format_amount_command(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let wei = parse_amount(&args["wei"], "wei")?;
+    let max_decimals = match &args["max_decimals"] {
+        serde_json::Value::Null => None,
+        value => Some(
+            value
+                .as_u64()
+                .and_then(|max| u32::try_from(max).ok())
+                .ok_or_else(|| anyhow::anyhow!("max_decimals must be a non-negative integer"))?,
+        ),
+    };
+    let options = amount::DisplayOptions {
+        unit: amount::Unit::parse(args["unit"].as_str().unwrap_or("eth"))?,
+        max_decimals,
+        thousands_separators: args["thousands_separators"].as_bool().unwrap_or(false),
+        trim_trailing_zeros: args["trim_trailing_zeros"].as_bool().unwrap_or(false),
+    };
+
+    Ok(serde_json::json!({
+        "formatted": amount::format_display(wei, &options),
+        "unit": options.unit.name(),
+        "wei": format_amount(wei)
+    }))
+}
+
+/// Converts `amount` (token base units) to the quote currency, or `quote_amount` back to base units.
+/// Precision, token decimals and rounding fall back to `config.conversion`; rounding may instead be
+/// derived from `direction` (`pay` floors, `receive` ceils).
+fn convert_amount(args: &serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+    let defaults = &config["conversion"];
+    let setting = |key: &str| if args[key].is_null() { &defaults[key] } else { &args[key] };
+    let small_int = |key: &str, default: u32| -> Result<u32> {
+        match setting(key) {
+            serde_json::Value::Null => Ok(default),
+            value => value
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| coded("INVALID_CONVERSION", format!("{} must be a non-negative integer", key))),
+        }
+    };
+    let token_decimals = small_int("token_decimals", 18)?;
+    let precision = small_int("precision", 2)?;
+    let rate = conversion::Decimal::parse(args["rate"].as_str().unwrap_or(""), "rate")?;
+    let rounding = match (setting("rounding").as_str(), setting("direction").as_str()) {
+        (Some(name), _) => conversion::Rounding::parse(name)?,
+        (None, Some(direction)) => conversion::Rounding::for_direction(direction)?,
+        (None, None) => {
+            return Err(coded("INVALID_CONVERSION", "Pass rounding (floor, ceil, half_even) or direction (pay, receive)"))
+        }
+    };
+
+    let (input, converted) = if args["quote_amount"].is_null() {
+        let amount = parse_amount(&args["amount"], "amount")?;
+        (format_amount(amount), conversion::to_quote(amount, token_decimals, rate, precision, rounding)?)
+    } else {
+        let quote = conversion::Decimal::parse(args["quote_amount"].as_str().unwrap_or(""), "quote_amount")?;
+        (quote.to_string(), conversion::from_quote(quote, token_decimals, rate, rounding)?)
+    };
+
+    let mut output = converted.to_json();
+    output["input"] = serde_json::json!(input);
+    output["token_decimals"] = serde_json::json!(token_decimals);
+    output["precision"] = serde_json::json!(if args["quote_amount"].is_null() { precision } else { 0 });
+    Ok(output)
+}
+
+/// Behavior-affecting changes between `since` and the running version.
+fn compat_report(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let since = args["since"].as_str().unwrap_or(compat::BASELINE_VERSION);
+    let running = env!("CARGO_PKG_VERSION");
+    let changes: Vec<serde_json::Value> = compat::changes_since(since, running)?
+        .into_iter()
+        .map(|change| serde_json::json!({
+            "version": change.version,
+            "kind": change.kind.name(),
+            "subject": change.subject,
+            "detail": (!change.detail.is_empty()).then_some(change.detail)
+        }))
+        .collect();
+
+    Ok(serde_json::json!({
+        "running_version": running,
+        "since": since,
+        "changes": changes
+    }))
+}
+
+fn list_environments(config: &serde_json::Value) -> Result<serde_json::Value> {
+    let path = environments::file_path(config);
+    let defined = environments::load(path)?;
+    let listed: Vec<serde_json::Value> = defined
+        .iter()
+        .map(|(name, layer)| serde_json::json!({ "name": name, "config": environments::redacted(layer) }))
+        .collect();
+
+    Ok(serde_json::json!({
+        "environments_file": path,
+        "environments": listed
+    }))
+}
+
+/// Public identity of the configured wallet; the private key never leaves this function.
+fn export_wallet(wallet_private_key: &str) -> Result<serde_json::Value> {
+    let signer = chain::signer_from_key(wallet_private_key)?;
+    let public_key = signer.credential().verifying_key();
+    Ok(serde_json::json!({
+        "address": signer.address().to_string(),
+        "public_key_uncompressed_hex": alloy::hex::encode_prefixed(public_key.to_encoded_point(false).as_bytes()),
+        "public_key_compressed_hex": alloy::hex::encode_prefixed(public_key.to_encoded_point(true).as_bytes())
+    }))
+}
+
+/// Applies a redaction profile to an Output or bundle file and writes the result to `output_path`.
+fn redact_output(args: &serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+    let input_path = args["input_path"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("input_path is required"))?;
+    let output_path = args["output_path"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("output_path is required"))?;
+    let profile = redaction::profile(config, args["profile"].as_str().unwrap_or(""))?;
+
+    let content = fs::read_to_string(input_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", input_path, e))?;
+    let mut document: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| coded("INVALID_INPUT", format!("{} is not JSON: {}", input_path, e)))?;
+    let summary = redaction::apply(&mut document, &profile);
+    fs::write(output_path, serde_json::to_string_pretty(&document)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", output_path, e))?;
+
+    Ok(serde_json::json!({
+        "output_path": output_path,
+        "profile": profile.name,
+        "fields_dropped": summary.dropped,
+        "fields_hashed": summary.hashed,
+        "invalidated_signatures": summary.invalidated_signatures
+    }))
+}
+
+/// Release provenance of this binary and whether its detached signature checks out.
+/// An unverified build is reported, not an error, unless `require_verified_build` is set.
+fn verify_build(config: &serde_json::Value) -> Result<serde_json::Value> {
+    let report = provenance::verify_running(config)?;
+    if let Some(problem) = &report.problem {
+        log::warn!("Build not verified: {}", problem);
+    }
+    Ok(report.to_json(&provenance::EMBEDDED))
+}
+
+fn verify_eip191_signature(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let message = args["message"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("message is required"))?;
+    let signature_hex = args["signature_hex"].as_str().unwrap_or("");
+    let recovered = alloy::primitives::Signature::from_str(signature_hex)
+        .map_err(|e| anyhow::anyhow!("Invalid signature_hex: {}", e))?
+        .recover_address_from_msg(message.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to recover signer: {}", e))?;
+    let matches_expected = args["expected_signer"]
+        .as_str()
+        .map(|expected| chain::parse_address(expected, "expected_signer"))
+        .transpose()?
+        .map(|expected| expected == recovered);
+
+    Ok(serde_json::json!({
+        "recovered_address": recovered.to_string(),
+        "matches_expected": matches_expected
+    }))
+}
+
+fn verify_webhook_signature(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let header = args["signature_header"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("signature_header is required"))?;
+    let body = args["body"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("body must be the raw request body as a string"))?;
+    // Consumers mid-rotation pass both the old and new secret
+    let secrets: Vec<String> = match (args["secrets"].as_array(), args["secret"].as_str()) {
+        (Some(secrets), _) => secrets.iter().filter_map(|s| s.as_str().map(str::to_string)).collect(),
+        (None, Some(secret)) => vec![secret.to_string()],
+        (None, None) => Vec::new(),
+    };
+    if secrets.is_empty() {
+        return Err(anyhow::anyhow!("secret or secrets is required"));
+    }
+    let tolerance_seconds = args["tolerance_seconds"].as_u64().unwrap_or(webhook::DEFAULT_TOLERANCE_SECONDS);
+
+    let verification = webhook::verify(header, body.as_bytes(), &secrets, unix_timestamp()?, tolerance_seconds)?;
+    Ok(serde_json::json!({
+        "valid": verification.valid,
+        "timestamp": verification.timestamp,
+        "secret_index": verification.secret_index,
+        "reason": verification.reason
+    }))
+}
+
+/// Text the user personal-signs (EIP-191) to let an operator sign claims on their behalf.
+fn operator_delegation_message(user: &alloy::primitives::Address, operator: &alloy::primitives::Address) -> String {
+    format!("4Mica operator delegation: {} may sign payment guarantees for {}", operator, user)
+}
+
+async fn sign_payment_for_operator(client: &Client, chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let claims = parse_claims(&args["claims"])?;
+    let operator = chain::parse_address(args["operator_address"].as_str().unwrap_or(""), "operator_address")?;
+    let user = chain::parse_address(&claims.user_address, "claims.user_address")?;
+    let delegation_signature = args["delegation_signature"].as_str().unwrap_or("");
+
+    // The configured wallet is the operator key in this flow
+    if chain.wallet_address != operator {
+        return Err(anyhow::anyhow!(
+            "Configured wallet {} is not the delegated operator {}",
+            chain.wallet_address, operator
+        ));
+    }
+
+    let message = operator_delegation_message(&user, &operator);
+    let delegator = alloy::primitives::Signature::from_str(delegation_signature)
+        .map_err(|e| anyhow::anyhow!("Invalid delegation_signature: {}", e))?
+        .recover_address_from_msg(message.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to recover delegation signer: {}", e))?;
+    if delegator != user {
+        return Err(coded(
+            "INVALID_DELEGATION",
+            format!("Delegation was signed by {}, not by user {}", delegator, user),
+        ));
+    }
+
+    let scheme = parse_signing_scheme(args["scheme"].as_str().unwrap_or("Eip712"));
+    match client.user.sign_payment(claims, scheme).await {
+        Ok(signature) => Ok(serde_json::json!({
+            "signature": signature.signature,
+            "scheme": format!("{:?}", signature.scheme),
+            "operator": operator.to_string(),
+            "delegation": {
+                "user": user.to_string(),
+                "operator": operator.to_string(),
+                "message": message,
+                "signature": delegation_signature
+            }
+        })),
+        Err(e) => Err(anyhow::anyhow!("Sign payment failed: {}", e))
+    }
+}
+
+async fn sign_payment_ecdsa_raw(
+    client: &Client,
+    wallet_private_key: &str,
+    args: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let signed = sign_payment(client, wallet_private_key, args).await?;
+    let mut split = split_ecdsa_signature(signed["signature"].as_str().unwrap_or(""))?;
+    split["scheme"] = signed["scheme"].clone();
+    if !signed["work_binding"].is_null() {
+        split["work_binding"] = signed["work_binding"].clone();
+    }
+    Ok(split)
+}
+
+/// Splits a 65-byte signature into `r`, `s` and a `v` of 27 or 28, whichever recovery id it was encoded with.
+fn split_ecdsa_signature(signature_hex: &str) -> Result<serde_json::Value> {
+    let bytes = alloy::hex::decode(signature_hex)
+        .map_err(|e| anyhow::anyhow!("Signature is not hex: {}", e))?;
+    let signature = alloy::primitives::Signature::try_from(bytes.as_slice())
+        .map_err(|e| anyhow::anyhow!("Signature is not a 65-byte ECDSA signature: {}", e))?;
+    Ok(serde_json::json!({
+        "r": alloy::primitives::B256::from(signature.r()).to_string(),
+        "s": alloy::primitives::B256::from(signature.s()).to_string(),
+        "v": 27 + u8::from(signature.v()),
+        "compact": alloy::hex::encode_prefixed(signature.as_bytes())
+    }))
+}
+
+/// Blocks of fee history an estimate is drawn from.
+const CONFIRMATION_HISTORY_BLOCKS: u64 = 20;
+
+/// Seconds per block when the chain is too short to measure, as on mainnet.
+const DEFAULT_BLOCK_TIME_SECONDS: f64 = 12.0;
+
+async fn get_estimated_confirmation_time(chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let max_fee_gwei = args["max_fee_per_gas_gwei"]
+        .as_f64()
+        .filter(|gwei| gwei.is_finite() && *gwei >= 0.0)
+        .ok_or_else(|| anyhow::anyhow!("max_fee_per_gas_gwei must be a non-negative number"))?;
+    let max_fee = (max_fee_gwei * 1e9).round() as u128;
+
+    let history = chain.provider
+        .get_fee_history(CONFIRMATION_HISTORY_BLOCKS, BlockNumberOrTag::Latest, &fees::REWARD_PERCENTILES)
+        .await
+        .map_err(|e| anyhow::anyhow!("Get fee history failed: {}", e))?;
+    let (next_base_fee, base_fees) = history
+        .base_fee_per_gas
+        .split_last()
+        .ok_or_else(|| anyhow::anyhow!("Empty fee history"))?;
+    let estimate = fees::estimate_confirmation(max_fee, *next_base_fee, base_fees, &history.reward.unwrap_or_default())
+        .ok_or_else(|| coded(
+            "FEE_BELOW_RECENT_RANGE",
+            format!(
+                "{} gwei is below every base fee of the last {} blocks; it may never confirm",
+                max_fee_gwei, CONFIRMATION_HISTORY_BLOCKS
+            ),
+        ))?;
+    let block_time = chain.mean_block_time(100).await
+        .map_err(|e| anyhow::anyhow!("Measure block time failed: {}", e))?
+        .unwrap_or(DEFAULT_BLOCK_TIME_SECONDS);
+
+    Ok(serde_json::json!({
+        "max_fee_gwei": max_fee_gwei,
+        "next_base_fee_wei": format_amount(U256::from(*next_base_fee)),
+        "tip_percentile": estimate.percentile,
+        "estimated_blocks": estimate.blocks,
+        "estimated_seconds": (estimate.blocks as f64 * block_time).ceil() as u64,
+        "confidence": estimate.confidence
+    }))
+}
+
+/// A week of 12-second blocks, matching the 168-hour cap on `window_hours`.
+const GAS_SAVINGS_MAX_BLOCKS: u64 = 50_400;
+
+/// What one `payTab` per block over the last `lookback_blocks` blocks or `window_hours` hours would have cost
+/// with legacy pricing versus EIP-1559, tipping each block's median priority fee. See
+/// [`fees::pricing_totals`] for the model.
+async fn get_gas_savings_estimate(chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let window_hours = match (args["window_hours"].as_u64(), args["lookback_blocks"].as_u64()) {
+        (Some(hours), None) if (1..=168).contains(&hours) => Some(hours),
+        (None, Some(blocks)) if (1..=GAS_SAVINGS_MAX_BLOCKS).contains(&blocks) => None,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Pass either window_hours between 1 and 168 or lookback_blocks between 1 and {}",
+                GAS_SAVINGS_MAX_BLOCKS
+            ))
+        }
+    };
+    let gas = args["gas_per_payment"].as_u64().unwrap_or(fees::PAY_TAB_GAS);
+    let headroom_percent = match args["legacy_headroom_percent"].as_u64() {
+        None => 100,
+        Some(percent) if percent <= 1000 => percent as u32,
+        Some(_) => return Err(anyhow::anyhow!("legacy_headroom_percent must be at most 1000")),
+    };
+
+    let latest = chain.provider.get_block_number().await?;
+    let from_block = match window_hours {
+        Some(hours) => {
+            let now = chain.block_timestamp(BlockNumberOrTag::Number(latest)).await?;
+            chain.first_block_at_or_after(now.saturating_sub(hours * 3600)).await?.min(latest)
+        }
+        None => (latest + 1).saturating_sub(args["lookback_blocks"].as_u64().unwrap_or(1)),
+    };
+    let history = fees::base_fee_history(chain, from_block, latest).await
+        .map_err(|e| anyhow::anyhow!("Get fee history failed: {}", e))?;
+    let blocks: Vec<(u128, u128)> =
+        history.samples.iter().zip(&history.median_tips).map(|((_, base_fee), tip)| (*base_fee, *tip)).collect();
+    let totals = fees::pricing_totals(&blocks, gas, headroom_percent);
+    let savings = totals.legacy.saturating_sub(totals.eip1559);
+
+    Ok(serde_json::json!({
+        "from_block": from_block,
+        "to_block": latest,
+        "window_hours": window_hours,
+        "blocks": blocks.len(),
+        "gas_per_payment": gas,
+        "legacy_headroom_percent": headroom_percent,
+        "legacy_total_wei": format_amount(U256::from(totals.legacy)),
+        "eip1559_total_wei": format_amount(U256::from(totals.eip1559)),
+        "savings_wei": format_amount(U256::from(savings)),
+        "savings_percent": if totals.legacy == 0 { 0.0 } else { savings as f64 * 100.0 / totals.legacy as f64 }
+    }))
+}
+
+async fn bootstrap(config: &serde_json::Value, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let target_collateral = parse_amount(&args["target_collateral_wei"], "target_collateral_wei")?;
+    let min_gas_balance =
+        parse_amount_or(&args["min_gas_balance_wei"], "min_gas_balance_wei", U256::from(10_000_000_000_000_000u64))?;
+    let poll_interval = std::time::Duration::from_secs(args["poll_interval_seconds"].as_u64().unwrap_or(15));
+    let funding_timeout = std::time::Duration::from_secs(args["funding_timeout_seconds"].as_u64().unwrap_or(3600));
+    let state_file = args["state_file"].as_str().unwrap_or("bootstrap-state.json");
+    let mut state = bootstrap::BootstrapState::load(state_file, "Bootstrap")?;
+    let resumed = !state.phases.is_empty();
+
+    // Phase 1: wallet. A key generated here is persisted so a re-run reuses it.
+    let wallet_private_key = match (&state.data.wallet_private_key, config["wallet_private_key"].as_str()) {
+        (Some(key), _) => key.clone(),
+        (None, Some(key)) => key.to_string(),
+        (None, None) => {
+            let key = alloy::hex::encode_prefixed(alloy::signers::local::PrivateKeySigner::random().to_bytes());
+            state.data.wallet_private_key = Some(key.clone());
+            key
+        }
+    };
+    let wallet_address = chain::signer_from_key(&wallet_private_key)?.address();
+    state.complete("wallet", serde_json::json!({ "address": wallet_address.to_string() }))?;
+
+    // Phase 2: connectivity against the API and the chain
+    let (client, chain) = connect(config, &wallet_private_key).await?;
+    let chain_id = chain.provider.get_chain_id().await
+        .map_err(|e| anyhow::anyhow!("Chain connectivity check failed: {}", e))?;
+    if let Err(e) = client.user.get_user().await {
+        return Err(anyhow::anyhow!("4Mica API connectivity check failed: {}", e));
+    }
+    state.complete("connectivity", serde_json::json!({ "chain_id": chain_id }))?;
+
+    // Phase 3: wait for the operator to fund gas
+    let started = std::time::Instant::now();
+    let balance = loop {
+        let balance = chain.provider.get_balance(wallet_address).await
+            .map_err(|e| anyhow::anyhow!("Get wallet balance failed: {}", e))?;
+        if balance >= min_gas_balance {
+            break balance;
+        }
+        if started.elapsed() >= funding_timeout {
+            return Err(coded(
+                "BOOTSTRAP_FUNDING_TIMEOUT",
+                format!(
+                    "Wallet {} still holds {} wei, needs {} wei; re-run to resume",
+                    wallet_address,
+                    format_amount(balance),
+                    format_amount(min_gas_balance)
+                ),
+            ));
+        }
+        estatus!(
+            "⏳ Waiting for {} to be funded: {} / {} wei",
+            wallet_address,
+            format_amount(balance),
+            format_amount(min_gas_balance)
+        );
+        tokio::time::sleep(poll_interval).await;
+    };
+    state.complete("funding", serde_json::json!({ "balance_wei": balance.to_string() }))?;
+    enforce_funding_floor(&chain, config, "bootstrap").await?;
+
+    // Phase 4: deposit only the shortfall, so a re-run never deposits twice
+    let collateral = match client.user.get_user().await {
+        Ok(user_info) => user_info.collateral,
+        Err(e) => return Err(anyhow::anyhow!("Get user failed: {}", e)),
+    };
+    if collateral < target_collateral {
+        let shortfall = target_collateral - collateral;
+        estatus!("💰 Depositing {} wei of collateral", format_amount(shortfall));
+        let receipt = match client.user.deposit(shortfall).await {
+            Ok(receipt) => receipt,
+            Err(e) => return Err(anyhow::anyhow!("Deposit failed: {}", e)),
+        };
+        state.complete("deposit", chain::receipt_json(&receipt))?;
+    } else {
+        state.complete("deposit", serde_json::json!({ "skipped": "collateral already at target" }))?;
+    }
+
+    // Phase 5: verify
+    let collateral = match client.user.get_user().await {
+        Ok(user_info) => user_info.collateral,
+        Err(e) => return Err(anyhow::anyhow!("Get user failed: {}", e)),
+    };
+    if collateral < target_collateral {
+        return Err(anyhow::anyhow!(
+            "Collateral is {} wei after deposit, below the {} wei target; re-run to resume",
+            collateral, target_collateral
+        ));
+    }
+    state.complete("verify", serde_json::json!({ "collateral_wei": collateral.to_string() }))?;
+
+    Ok(serde_json::json!({
+        "wallet_address": wallet_address.to_string(),
+        "collateral_wei": collateral.to_string(),
+        "resumed": resumed,
+        "phases": state.phases
+    }))
+}
+
+/// Moves all collateral from the configured wallet to a successor agent: request a
+/// withdrawal, finalize it once the delay has passed, send the ETH across and deposit it
+/// from the new wallet. Each phase is checkpointed, so a run that stops (typically on a
+/// delay longer than `max_wait_seconds`) is finished by re-running it. The new wallet
+/// receives exactly the collateral it deposits, so it must already hold the deposit's gas.
+async fn transfer_collateral_to_new_agent(
+    client: &Client,
+    chain: &Chain,
+    config: &serde_json::Value,
+    args: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let new_agent = chain::parse_address(args["new_agent_address"].as_str().unwrap_or(""), "new_agent_address")?;
+    let new_agent_key = args["new_agent_private_key"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("new_agent_private_key is required to deposit from the new wallet"))?;
+    if chain::signer_from_key(new_agent_key)?.address() != new_agent {
+        return Err(coded("NEW_AGENT_KEY_MISMATCH", format!("new_agent_private_key does not belong to {}", new_agent)));
+    }
+    if new_agent == chain.wallet_address {
+        return Err(anyhow::anyhow!("new_agent_address is the configured wallet"));
+    }
+    let max_wait_seconds = args["max_wait_seconds"].as_u64().unwrap_or(0);
+    let state_file = args["state_file"].as_str().unwrap_or("handoff-state.json");
+    let mut state = handoff::HandoffState::load(state_file, "Handoff")?;
+    let resumed = !state.phases.is_empty();
+    if state.data.new_agent_address.as_deref().is_some_and(|address| address != new_agent.to_string()) {
+        return Err(anyhow::anyhow!("State file belongs to a handoff to a different agent"));
+    }
+    state.data.old_agent_address = Some(chain.wallet_address.to_string());
+    state.data.new_agent_address = Some(new_agent.to_string());
+
+    // Refuse before anything is withdrawn if the new wallet could not pay for its deposit
+    let mut deposit_gas_cost = None;
+    if !state.is_complete("deposit") {
+        let gas_price = chain.provider.get_gas_price().await
+            .map_err(|e| anyhow::anyhow!("Get gas price failed: {}", e))?;
+        let gas_cost = U256::from(gas_price).saturating_mul(U256::from(fees::DEPOSIT_GAS));
+        let balance = chain.provider.get_balance(new_agent).await
+            .map_err(|e| anyhow::anyhow!("Get balance of {} failed: {}", new_agent, e))?;
+        let transferred = match &state.data.amount_wei {
+            Some(amount) if state.is_complete("transfer") => parse_amount_str(amount, "handoff amount_wei")?,
+            _ => U256::ZERO,
+        };
+        if balance < transferred.saturating_add(gas_cost) {
+            return Err(coded(
+                "NEW_AGENT_UNFUNDED",
+                format!(
+                    "{} has {} wei for gas but its deposit needs about {} wei; fund it and re-run",
+                    new_agent,
+                    format_amount(balance.saturating_sub(transferred)),
+                    format_amount(gas_cost)
+                ),
+            ));
+        }
+        deposit_gas_cost = Some(gas_cost);
+    }
+
+    // Phase 1: request withdrawal of everything, or adopt a request already pending
+    if !state.is_complete("request_withdrawal") {
+        let user_info = client.user.get_user().await
+            .map_err(|e| anyhow::anyhow!("Get user failed: {}", e))?;
+        let (amount, result) = if !user_info.withdrawal_request_amount.is_zero() {
+            let amount = user_info.withdrawal_request_amount;
+            (amount, serde_json::json!({ "adopted_pending_request": true, "amount_wei": format_amount(amount) }))
+        } else if user_info.collateral.is_zero() {
+            return Err(coded("NO_COLLATERAL", format!("{} has no collateral to transfer", chain.wallet_address)));
+        } else {
+            let amount = user_info.collateral;
+            let receipt = client.user.request_withdrawal(amount).await
+                .map_err(|e| anyhow::anyhow!("Request withdrawal of {} wei failed: {}", format_amount(amount), e))?;
+            (amount, chain::receipt_json(&receipt))
+        };
+        state.data.amount_wei = Some(format_amount(amount));
+        state.complete("request_withdrawal", result)?;
+    }
+    let amount = parse_amount_str(state.data.amount_wei.as_deref().unwrap_or(""), "handoff amount_wei")?;
+
+    // Phase 2: finalize on the old wallet, retried until the withdrawal delay has passed or
+    // max_wait_seconds runs out; the delay itself is only known to the contract
+    if !state.is_complete("finalize_withdrawal") {
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(max_wait_seconds);
+        let receipt = loop {
+            match client.user.finalize_withdrawal().await {
+                Ok(receipt) => break receipt,
+                Err(e) if tokio::time::Instant::now() >= deadline => {
+                    return Err(coded(
+                        "WITHDRAWAL_DELAY_PENDING",
+                        format!("Finalize withdrawal failed: {}; re-run after the withdrawal delay to resume", e),
+                    ));
+                }
+                Err(e) => {
+                    estatus!("⏳ Finalize withdrawal not possible yet ({}); retrying", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(15)).await;
+                }
+            }
+        };
+        state.complete("finalize_withdrawal", chain::receipt_json(&receipt))?;
+    }
+
+    // Phase 3: send the withdrawn ETH to the new wallet
+    if !state.is_complete("transfer") {
+        use alloy::network::TransactionBuilder;
+        let tx = alloy::rpc::types::TransactionRequest::default().with_to(new_agent).with_value(amount);
+        let receipt = chain.provider.send_transaction(tx).await
+            .map_err(|e| anyhow::anyhow!("Transfer of {} wei to {} failed: {}", format_amount(amount), new_agent, e))?
+            .get_receipt()
+            .await?;
+        let receipt = chain::ensure_success(receipt, "Transfer to the new agent")?;
+        state.complete("transfer", chain::receipt_json(&receipt))?;
+    }
+
+    // Phase 4: deposit from the new wallet
+    if !state.is_complete("deposit") {
+        let (new_client, _) = connect(config, new_agent_key).await?;
+        estatus!("💰 Depositing {} wei of collateral for {}", format_amount(amount), new_agent);
+        let receipt = new_client.user.deposit(amount).await
+            .map_err(|e| anyhow::anyhow!("Deposit from {} failed: {}", new_agent, e))?;
+        state.complete("deposit", chain::receipt_json(&receipt))?;
+    }
+
+    Ok(serde_json::json!({
+        "old_agent_address": chain.wallet_address.to_string(),
+        "new_agent_address": new_agent.to_string(),
+        "amount_wei": format_amount(amount),
+        "deposit_gas_cost_wei": deposit_gas_cost.map(format_amount),
+        "resumed": resumed,
+        "phases": state.phases
+    }))
+}
+
+/// Chain id of a local anvil node, the only chain `simulate_flow` runs against.
+const ANVIL_CHAIN_ID: u64 = 31337;
+
+/// Runs deposit, create_tab, `rounds` sign/guarantee rounds (the API verifies each signature) and
+/// settlement end to end, returning every intermediate artifact. The chain is snapshotted first and reverted afterwards
+/// (unless `keep_state`), so repeated runs start from the same state.
+async fn simulate_flow(client: &Client, chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let rounds = args["rounds"].as_u64().unwrap_or(3);
+    let amount_per_round = parse_amount_or(
+        &args["amount_per_round_wei"],
+        "amount_per_round_wei",
+        U256::from(1_000_000_000_000_000u64),
+    )?;
+    let total = amount_per_round
+        .checked_mul(U256::from(rounds))
+        .ok_or_else(|| anyhow::anyhow!("rounds * amount_per_round_wei overflows uint256"))?;
+    let recipient_address = match args["recipient_address"].as_str() {
+        Some(address) => chain::parse_address(address, "recipient_address")?,
+        None => chain.wallet_address,
+    };
+
+    let chain_id = chain.provider.get_chain_id().await
+        .map_err(|e| anyhow::anyhow!("Get chain id failed: {}", e))?;
+    if chain_id != ANVIL_CHAIN_ID {
+        return Err(coded(
+            "SIMULATION_REFUSED",
+            format!(
+                "simulate_flow only runs against a local anvil node (chain id {}), not chain {}",
+                ANVIL_CHAIN_ID, chain_id
+            ),
+        ));
+    }
+    let snapshot: serde_json::Value = chain.provider.raw_request("evm_snapshot".into(), ()).await
+        .map_err(|e| anyhow::anyhow!("evm_snapshot failed: {}", e))?;
+
+    let mut steps = Vec::new();
+    let result = run_simulated_flow(client, chain, recipient_address, rounds, amount_per_round, total, &mut steps).await;
+
+    let reverted = if args["keep_state"].as_bool().unwrap_or(false) {
+        false
+    } else {
+        chain.provider.raw_request::<_, bool>("evm_revert".into(), (snapshot.clone(),)).await
+            .map_err(|e| anyhow::anyhow!("evm_revert to snapshot {} failed: {}", snapshot, e))?
+    };
+
+    match result {
+        Ok(()) => Ok(serde_json::json!({
+            "rounds": rounds,
+            "total_wei": format_amount(total),
+            "reverted": reverted,
+            "transcript": steps
+        })),
+        Err(e) => {
+            steps.push(Step::failed(
+                "simulate_flow",
+                error::error_code(&e).as_deref().unwrap_or("SIMULATION_FAILED"),
+                e.to_string(),
+            ));
+            Err(PartialFailure { steps, resume: Vec::new(), data: serde_json::json!({ "reverted": reverted }) }.into())
+        }
+    }
+}
+
+async fn run_simulated_flow(
+    client: &Client,
+    chain: &Chain,
+    recipient: alloy::primitives::Address,
+    rounds: u64,
+    amount_per_round: U256,
+    total: U256,
+    steps: &mut Vec<Step>,
+) -> Result<()> {
+    let user = chain.wallet_address;
+
+    let receipt = client.user.deposit(total).await.map_err(|e| anyhow::anyhow!("Deposit failed: {}", e))?;
+    steps.push(Step::completed("deposit", chain::receipt_json(&receipt)));
+
+    let request = serde_json::json!({
+        "user_address": user.to_string(),
+        "recipient_address": recipient.to_string(),
+        "ttl": 3600
+    });
+    let created = client.recipient.create_tab(user.to_string(), recipient.to_string(), Some(3600));
+    let tab_id = wirelog::api_call("create_tab", request, created).await
+        .map_err(|e| anyhow::anyhow!("Create tab failed: {}", e))?;
+    steps.push(Step::completed("create_tab", serde_json::json!({ "tab_id": tab_id.to_string() })));
+
+    for round in 1..=rounds {
+        let claims = PaymentGuaranteeClaims {
+            user_address: user.to_string(),
+            recipient_address: recipient.to_string(),
+            tab_id,
+            req_id: U256::from(round),
+            amount: amount_per_round,
+            timestamp: unix_timestamp()?,
+        };
+        steps.push(Step::completed(format!("round_{}_claims", round), claims_json(&claims)));
+
+        let signature = client.user.sign_payment(claims.clone(), SigningScheme::Eip712).await
+            .map_err(|e| anyhow::anyhow!("Sign payment failed: {}", e))?;
+        steps.push(Step::completed(
+            format!("round_{}_sign", round),
+            serde_json::json!({ "signature": signature.signature }),
+        ));
+
+        let logged = guarantee_call_args(&claims, &signature.signature, &SigningScheme::Eip712);
+        let issued = client.recipient.issue_payment_guarantee(claims, signature.signature, SigningScheme::Eip712);
+        let certificate = wirelog::api_call("issue_payment_guarantee", logged, issued)
+            .await
+            .map_err(|e| anyhow::anyhow!("Issue payment guarantee failed: {}", e))?;
+        steps.push(Step::completed(
+            format!("round_{}_issue_guarantee", round),
+            serde_json::json!({ "certificate": format!("{:?}", certificate) }),
+        ));
+    }
+
+    let receipt = client.user.pay_tab(tab_id, U256::from(rounds), total, recipient.to_string()).await
+        .map_err(|e| anyhow::anyhow!("Pay tab failed: {}", e))?;
+    steps.push(Step::completed("settle", chain::receipt_json(&receipt)));
+    Ok(())
+}
+
+/// Times `iterations` (default 10) successive calls of each common read-only RPC method.
+async fn benchmark_rpc(chain: &Chain, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let iterations = args["iterations"].as_u64().unwrap_or(10).clamp(1, 1000);
+    let mut methods = Vec::new();
+    for method in ["eth_blockNumber", "eth_getBalance", "eth_call"] {
+        let mut samples = Vec::new();
+        for _ in 0..iterations {
+            let started = std::time::Instant::now();
+            let result = match method {
+                "eth_blockNumber" => chain.provider.get_block_number().await.map(|_| ()).map_err(anyhow::Error::from),
+                "eth_getBalance" => {
+                    chain.provider.get_balance(chain.wallet_address).await.map(|_| ()).map_err(anyhow::Error::from)
+                }
+                _ => {
+                    // A call to the wallet itself returns nothing and cannot revert
+                    use alloy::network::TransactionBuilder;
+                    let call = alloy::rpc::types::TransactionRequest::default().with_to(chain.wallet_address);
+                    chain.provider.call(call).await.map(|_| ()).map_err(anyhow::Error::from)
+                }
+            };
+            result.map_err(|e| anyhow::anyhow!("{} failed: {}", method, e))?;
+            samples.push(started.elapsed().as_secs_f64() * 1000.0);
+        }
+        samples.sort_by(f64::total_cmp);
+        methods.push(serde_json::json!({
+            "method": method,
+            "min_ms": samples[0],
+            "max_ms": samples[samples.len() - 1],
+            "p50_ms": percentile(&samples, 50),
+            "p95_ms": percentile(&samples, 95)
+        }));
+    }
+
+    Ok(serde_json::json!({
+        "iterations": iterations,
+        "methods": methods
+    }))
+}
+
+/// Nearest-rank percentile of ascending `samples`.
+fn percentile(samples: &[f64], percent: usize) -> f64 {
+    let rank = (percent * samples.len()).div_ceil(100).max(1);
+    samples[rank - 1]
+}
+
+async fn get_contract_upgrade_status(chain: &Chain) -> Result<serde_json::Value> {
+    let status = chain
+        .proxy_status()
+        .await
+        .map_err(|e| anyhow::anyhow!("Read proxy storage slots failed: {}", e))?;
+
+    Ok(serde_json::json!({
+        "contract_address": chain.contract_address.to_string(),
+        "is_proxy": status.is_some(),
+        "proxy_pattern": status.as_ref().map(|status| status.pattern),
+        "implementation_address": status.as_ref().map(|status| status.implementation.to_string()),
+        "admin_address": status.as_ref().and_then(|status| status.admin).map(|admin| admin.to_string())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const OFFLINE_COMMANDS: &[&str] = &[
+        "compat_report",
+        "verify_work_binding",
+        "list_environments",
+        "convert_amount",
+        "verify_certificate_quorum",
+        "compare_outputs",
+        "sign_typed_data",
+        "verify_typed_data",
+        "verify_eip191_signature",
+        "verify_webhook_signature",
+        "verify_payment_signature",
+        "export_wallet",
+        "format_amount",
+        "import_certs",
+        "compute_required_collateral",
+        "redact_output",
+        "verify_build",
+    ];
+
+    #[test]
+    fn offline_command_list_matches_the_dispatcher() {
+        for (command, _) in commands::COMMANDS {
+            let input =
+                Input { command: command.to_string(), args: serde_json::json!({}), config: serde_json::json!({}) };
+            assert_eq!(
+                run_offline_command(&input, DEFAULT_WALLET_PRIVATE_KEY).is_some(),
+                OFFLINE_COMMANDS.contains(command),
+                "{} is missing from OFFLINE_COMMANDS or run_offline_command",
+                command
+            );
+        }
+    }
+
+    fn arb_args() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(serde_json::Value::Null),
+            any::<bool>().prop_map(serde_json::Value::from),
+            any::<u64>().prop_map(serde_json::Value::from),
+            "(0x)?[0-9a-fA-F]{0,70}".prop_map(serde_json::Value::from),
+            ".*".prop_map(serde_json::Value::from),
+        ];
+        leaf.prop_recursive(6, 48, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(serde_json::Value::from),
+                prop::collection::btree_map(
+                    prop_oneof![
+                        Just("certificate".to_string()),
+                        Just("signers".to_string()),
+                        Just("operator_set".to_string()),
+                        Just("operator".to_string()),
+                        Just("weight".to_string()),
+                        Just("quorum_threshold_basis_points".to_string()),
+                        Just("typed_data".to_string()),
+                        Just("signature".to_string()),
+                        Just("message".to_string()),
+                        Just("signature_hex".to_string()),
+                        Just("claims".to_string()),
+                        ".*",
+                    ],
+                    inner,
+                    0..6
+                )
+                .prop_map(|m| serde_json::Value::Object(m.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn offline_commands_never_panic(command in prop::sample::select(OFFLINE_COMMANDS), args in arb_args()) {
+            let input = Input { command: command.to_string(), args, config: serde_json::json!({}) };
+            let result = run_offline_command(&input, DEFAULT_WALLET_PRIVATE_KEY).expect("offline command");
+            let output: serde_json::Value = serde_json::from_str(&render_output(result)).unwrap();
+            prop_assert!(output["success"].is_boolean());
+            if output["success"] == false {
+                prop_assert!(output["error"].is_string());
+            }
+        }
+    }
+
+    #[test]
+    fn quorum_weights_that_overflow_are_rejected() {
+        let args = serde_json::json!({
+            "certificate": { "signers": ["0xa", "0xb"] },
+            "operator_set": [
+                { "operator": "0xa", "weight": U256::MAX.to_string() },
+                { "operator": "0xb", "weight": "1" }
+            ],
+            "quorum_threshold_basis_points": 6667
+        });
+        assert!(verify_certificate_quorum(&args).is_err());
+
+        let args = serde_json::json!({
+            "certificate": { "signers": ["0xa"] },
+            "operator_set": [{ "operator": "0xa", "weight": U256::MAX.to_string() }],
+            "quorum_threshold_basis_points": 6667
+        });
+        assert!(verify_certificate_quorum(&args).is_err());
+    }
+
+    /// Stands in for the SDK's payment signature, which the voucher carries but does not check.
+    fn payment_signature(signer: &alloy::signers::local::PrivateKeySigner) -> String {
+        use alloy::signers::SignerSync;
+        signer.sign_message_sync(b"payment").unwrap().to_string()
+    }
+
+    #[test]
+    fn vouchers_round_trip_and_detect_tampering() {
+        let signer = chain::signer_from_key(DEFAULT_WALLET_PRIVATE_KEY).unwrap();
+        let claims = parse_claims(&serde_json::json!({
+            "user_address": signer.address().to_string(),
+            "recipient_address": "0x00000000000000000000000000000000000000b0",
+            "tab_id": "7",
+            "req_id": "1",
+            "amount": "1000",
+            "timestamp": 1_700_000_000u64
+        }))
+        .unwrap();
+        let voucher =
+            seal_voucher(&signer, claims_json(&claims), payment_signature(&signer), "Eip712".into(), None).unwrap();
+
+        let args = serde_json::json!({ "voucher": voucher });
+        assert_eq!(verify_payment_signature(&args).unwrap()["valid"], true);
+
+        let mut tampered = args.clone();
+        tampered["voucher"]["claims"]["amount"] = serde_json::json!("1000000");
+        assert_eq!(verify_payment_signature(&tampered).unwrap()["valid"], false);
+        let mut tampered = args.clone();
+        tampered["voucher"]["scheme"] = serde_json::json!("Eip191");
+        assert_eq!(verify_payment_signature(&tampered).unwrap()["valid"], false);
+
+        let mut future = args;
+        future["voucher"]["version"] = serde_json::json!(2);
+        let err = verify_payment_signature(&future).unwrap_err();
+        assert_eq!(error::error_code(&err).as_deref(), Some("UNSUPPORTED_VOUCHER_VERSION"));
+    }
+
+    #[test]
+    fn ids_are_required_strings() {
+        let args = serde_json::json!({ "tab_id": "0x10", "req_id": 3, "bad": "12abc" });
+        assert_eq!(parse_id(&args["tab_id"], "tab_id").unwrap(), U256::from(16));
+        for field in ["req_id", "missing", "bad"] {
+            let err = parse_id(&args[field], field).unwrap_err();
+            assert_eq!(error::error_code(&err).as_deref(), Some("INVALID_INPUT"), "{}", field);
+        }
+    }
+
+    #[test]
+    fn work_binding_failures_are_distinct_from_signature_failures() {
+        let signer = chain::signer_from_key(DEFAULT_WALLET_PRIVATE_KEY).unwrap();
+        let claims = parse_claims(&serde_json::json!({
+            "user_address": signer.address().to_string(),
+            "recipient_address": "0x00000000000000000000000000000000000000b0",
+            "tab_id": "7",
+            "req_id": "1",
+            "amount": "1000",
+            "timestamp": 1_700_000_000u64
+        }))
+        .unwrap();
+        let mut voucher =
+            seal_voucher(&signer, claims_json(&claims), payment_signature(&signer), "Eip712".into(), None).unwrap();
+        let delivered = workbinding::hash_artifact(b"agent output");
+        voucher.work_binding = Some(workbinding::bind(&signer, &voucher.signature, delivered).unwrap());
+
+        let args = |voucher: &voucher::Voucher, work: &[u8]| {
+            serde_json::json!({ "voucher": voucher, "work_hash": workbinding::hash_artifact(work).to_string() })
+        };
+        assert_eq!(verify_work_binding(&args(&voucher, b"agent output")).unwrap()["valid"], true);
+        assert_eq!(verify_work_binding(&args(&voucher, b"other output")).unwrap()["failure"], "WORK_HASH_MISMATCH");
+
+        let mut tampered = args(&voucher, b"agent output");
+        tampered["voucher"]["claims"]["amount"] = serde_json::json!("1000000");
+        assert_eq!(verify_work_binding(&tampered).unwrap()["failure"], "VOUCHER_TAMPERED");
+
+        voucher.work_binding = None;
+        assert_eq!(verify_work_binding(&args(&voucher, b"agent output")).unwrap()["failure"], "NO_WORK_BINDING");
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let samples: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&samples, 50), 5.0);
+        assert_eq!(percentile(&samples, 95), 10.0);
+        assert_eq!(percentile(&samples[..1], 95), 1.0);
+    }
+
+    #[test]
+    fn env_flag_is_taken_out_of_the_positional_arguments() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            split_env_flag(args(&["bin", "--env", "demo", "in.json", "out.json"])),
+            (Some("demo".to_string()), args(&["bin", "in.json", "out.json"]))
+        );
+        assert_eq!(split_env_flag(args(&["bin", "in.json", "out.json", "--env=staging"])).0.as_deref(), Some("staging"));
+        assert_eq!(split_env_flag(args(&["bin", "in.json", "out.json"])).0, None);
+    }
+
+    #[test]
+    fn export_wallet_derives_public_identity_only() {
+        let exported = export_wallet(DEFAULT_WALLET_PRIVATE_KEY).unwrap();
+        let signer = chain::signer_from_key(DEFAULT_WALLET_PRIVATE_KEY).unwrap();
+        assert_eq!(exported["address"], signer.address().to_string());
+
+        let uncompressed = alloy::hex::decode(exported["public_key_uncompressed_hex"].as_str().unwrap()).unwrap();
+        assert_eq!(uncompressed.len(), 65);
+        assert_eq!(alloy::primitives::Address::from_raw_public_key(&uncompressed[1..]), signer.address());
+        assert_eq!(exported["public_key_compressed_hex"].as_str().unwrap().len(), 2 + 66);
+
+        let key = DEFAULT_WALLET_PRIVATE_KEY.trim_start_matches("0x").to_lowercase();
+        assert!(!exported.to_string().to_lowercase().contains(&key));
+    }
+
+    #[test]
+    fn ecdsa_signatures_split_into_r_s_v() {
+        let r = "11".repeat(32);
+        let s = "22".repeat(32);
+        for (v_byte, v) in [("1b", 27), ("1c", 28), ("00", 27), ("01", 28)] {
+            let split = split_ecdsa_signature(&format!("0x{}{}{}", r, s, v_byte)).unwrap();
+            assert_eq!(split["r"], format!("0x{}", r));
+            assert_eq!(split["s"], format!("0x{}", s));
+            assert_eq!(split["v"], v);
+            assert_eq!(split["compact"], format!("0x{}{}{}", r, s, if v == 27 { "1b" } else { "1c" }));
+        }
+        assert!(split_ecdsa_signature("0x1234").is_err());
+    }
+}
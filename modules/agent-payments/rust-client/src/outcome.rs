@@ -0,0 +1,79 @@
+//! Partial-success reporting for commands that commit several steps.
+//!
+//! When a later step fails after earlier ones committed (a tab was created,
+//! a transaction broadcast), the command returns a `PartialFailure` instead of
+//! a plain error so the orchestrator can see what happened and retry only the
+//! failed part using the `resume` hints.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub name: String,
+    pub status: String,
+    pub artifacts: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+}
+
+impl Step {
+    pub fn completed(name: impl Into<String>, artifacts: serde_json::Value) -> Self {
+        Step {
+            name: name.into(),
+            status: "completed".to_string(),
+            artifacts,
+            error: None,
+            error_code: None,
+        }
+    }
+
+    pub fn failed(name: impl Into<String>, error_code: &str, error: impl Into<String>) -> Self {
+        Step {
+            name: name.into(),
+            status: "failed".to_string(),
+            artifacts: serde_json::Value::Null,
+            error: Some(error.into()),
+            error_code: Some(error_code.to_string()),
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status == "failed"
+    }
+}
+
+#[derive(Debug)]
+pub struct PartialFailure {
+    pub steps: Vec<Step>,
+    /// Invocations (`{ command, args }`) that retry just the failed steps.
+    pub resume: Vec<serde_json::Value>,
+    /// Data still worth returning alongside the failure.
+    pub data: serde_json::Value,
+}
+
+impl PartialFailure {
+    pub fn first_failed(&self) -> Option<&Step> {
+        self.steps.iter().find(|step| step.is_failed())
+    }
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let committed = self.steps.iter().filter(|step| !step.is_failed()).count();
+        match self.first_failed() {
+            Some(step) => write!(
+                f,
+                "Step {} failed after {} committed step(s): {}",
+                step.name,
+                committed,
+                step.error.as_deref().unwrap_or("unknown error")
+            ),
+            None => write!(f, "Partial failure with {} committed step(s)", committed),
+        }
+    }
+}
+
+impl std::error::Error for PartialFailure {}
@@ -0,0 +1,155 @@
+//! Release provenance of the running binary.
+//!
+//! The release pipeline sets `FOURMICA_BUILD_COMMIT`, `FOURMICA_BUILD_TIMESTAMP`
+//! and `FOURMICA_RELEASE_SIGNER` (the pinned release address) at compile time,
+//! then signs the finished binary. A signature cannot live inside the bytes it
+//! covers, so it ships detached next to the binary as `<binary>.sig`: an
+//! EIP-191 signature by the release key over [`message`], which names the
+//! binary's SHA-256 and the embedded commit. Local and CI builds carry no
+//! signer and never verify.
+
+use crate::error::coded;
+use alloy::primitives::{Address, Signature, B256};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Provenance<'a> {
+    pub version: &'a str,
+    pub git_commit: Option<&'a str>,
+    pub build_timestamp: Option<&'a str>,
+    pub release_signer: Option<&'a str>,
+}
+
+pub const EMBEDDED: Provenance<'static> = Provenance {
+    version: env!("CARGO_PKG_VERSION"),
+    git_commit: option_env!("FOURMICA_BUILD_COMMIT"),
+    build_timestamp: option_env!("FOURMICA_BUILD_TIMESTAMP"),
+    release_signer: option_env!("FOURMICA_RELEASE_SIGNER"),
+};
+
+#[derive(Debug)]
+pub struct Report {
+    pub sha256: B256,
+    pub signature_file: String,
+    /// Why the binary is not verified; `None` when it is.
+    pub problem: Option<String>,
+}
+
+impl Report {
+    pub fn to_json(&self, provenance: &Provenance) -> serde_json::Value {
+        serde_json::json!({
+            "verified": self.problem.is_none(),
+            "problem": self.problem,
+            "sha256": self.sha256.to_string(),
+            "signature_file": self.signature_file,
+            "version": provenance.version,
+            "git_commit": provenance.git_commit,
+            "build_timestamp": provenance.build_timestamp,
+            "release_signer": provenance.release_signer
+        })
+    }
+}
+
+fn message(sha256: B256, provenance: &Provenance) -> String {
+    format!(
+        "fourmica-client release {}\ncommit: {}\nsha256: {}",
+        provenance.version,
+        provenance.git_commit.unwrap_or("unknown"),
+        sha256
+    )
+}
+
+/// Why `signature` (the detached file's contents, if it exists) does not vouch for a binary
+/// hashing to `sha256`, or `None` if it does.
+pub fn problem(sha256: B256, signature: Option<&str>, provenance: &Provenance) -> Option<String> {
+    let Some(signer) = provenance.release_signer else {
+        return Some("not a release build: no release signer is embedded".to_string());
+    };
+    let Ok(signer) = Address::from_str(signer) else {
+        return Some(format!("embedded release signer '{}' is not an address", signer));
+    };
+    let Some(signature) = signature else {
+        return Some("no release signature found".to_string());
+    };
+    let Ok(signature) = Signature::from_str(signature.trim()) else {
+        return Some("release signature is malformed".to_string());
+    };
+    match signature.recover_address_from_msg(message(sha256, provenance).as_bytes()) {
+        Ok(recovered) if recovered == signer => None,
+        _ => Some("release signature does not match this binary".to_string()),
+    }
+}
+
+/// Hashes the running binary and checks it against its detached signature.
+pub fn verify_running(config: &serde_json::Value) -> Result<Report> {
+    let exe = std::env::current_exe().map_err(|e| anyhow::anyhow!("Cannot locate the running binary: {}", e))?;
+    // The binary cannot change under a running process, so it is hashed once
+    static SHA256: OnceLock<B256> = OnceLock::new();
+    let sha256 = match SHA256.get() {
+        Some(sha256) => *sha256,
+        None => {
+            let bytes =
+                std::fs::read(&exe).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", exe.display(), e))?;
+            *SHA256.get_or_init(|| B256::from_slice(&Sha256::digest(&bytes)))
+        }
+    };
+    let signature_file = match config["build_signature_file"].as_str() {
+        Some(path) => path.to_string(),
+        None => format!("{}.sig", exe.display()),
+    };
+    let signature = std::fs::read_to_string(&signature_file).ok();
+    Ok(Report {
+        sha256,
+        problem: problem(sha256, signature.as_deref(), &EMBEDDED),
+        signature_file,
+    })
+}
+
+/// With `require_verified_build: true`, refuses to run an unverified binary.
+pub fn enforce(config: &serde_json::Value) -> Result<()> {
+    if !config["require_verified_build"].as_bool().unwrap_or(false) {
+        return Ok(());
+    }
+    let report = verify_running(config)?;
+    match report.problem {
+        Some(problem) => Err(coded("BUILD_NOT_VERIFIED", format!("require_verified_build is set: {}", problem))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn only_the_pinned_signer_vouches_for_the_hash() {
+        let release_key = PrivateKeySigner::random();
+        let signer = release_key.address().to_string();
+        let provenance = Provenance {
+            version: "0.2.0",
+            git_commit: Some("abc123"),
+            build_timestamp: None,
+            release_signer: Some(&signer),
+        };
+        let sha256 = B256::repeat_byte(0x11);
+        let sign = |key: &PrivateKeySigner, sha256| {
+            key.sign_message_sync(message(sha256, &provenance).as_bytes()).unwrap().to_string()
+        };
+
+        assert_eq!(problem(sha256, Some(&format!("{}\n", sign(&release_key, sha256))), &provenance), None);
+        let tampered = problem(B256::repeat_byte(0x22), Some(&sign(&release_key, sha256)), &provenance);
+        assert_eq!(tampered.as_deref(), Some("release signature does not match this binary"));
+        let impostor = problem(sha256, Some(&sign(&PrivateKeySigner::random(), sha256)), &provenance);
+        assert_eq!(impostor.as_deref(), Some("release signature does not match this binary"));
+        assert_eq!(problem(sha256, None, &provenance).as_deref(), Some("no release signature found"));
+
+        let unsigned_build = Provenance { release_signer: None, ..provenance };
+        let unsigned = problem(sha256, Some(&sign(&release_key, sha256)), &unsigned_build);
+        assert!(unsigned.unwrap().starts_with("not a release build"));
+    }
+}
@@ -0,0 +1,282 @@
+//! Named redaction profiles for Outputs and bundles handed to third parties.
+//!
+//! `config.redaction_profiles` maps a profile name to JSON pointers to drop
+//! or hash:
+//!
+//! ```json
+//! { "partner": { "drop": ["/balance_wei", "/guarantees/*/metadata"], "hash": ["/user_address"] } }
+//! ```
+//!
+//! A `*` token matches every element of an array or object. Hashed values
+//! become `sha256:0x…` of their JSON text, so equal values stay comparable.
+//! `redact_output` applies a profile to an existing file and
+//! `config.output_redaction` applies one to this run's Output.
+//!
+//! Redacting a field can break a signature over it (a voucher's claims, a
+//! certificate export). Only the original signer could re-sign, so any object
+//! carrying a `signature` above a redacted field has the signature nulled and
+//! a `signature_invalidated` note added instead of a stale signature left behind.
+
+use crate::error::coded;
+use anyhow::Result;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub name: String,
+    pub drop: Vec<String>,
+    pub hash: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub dropped: usize,
+    pub hashed: usize,
+    /// Pointers, into the unredacted document, of the objects whose signatures were invalidated.
+    pub invalidated_signatures: Vec<String>,
+}
+
+static INLINE: OnceLock<Profile> = OnceLock::new();
+
+pub fn profile(config: &Value, name: &str) -> Result<Profile> {
+    let entry = &config["redaction_profiles"][name];
+    if !entry.is_object() {
+        return Err(coded("REDACTION_PROFILE_NOT_FOUND", format!("No redaction profile named {}", name)));
+    }
+    let pointers = |key: &str| -> Result<Vec<String>> {
+        let list = match &entry[key] {
+            Value::Null => return Ok(Vec::new()),
+            Value::Array(list) => list,
+            _ => return Err(coded("INVALID_INPUT", format!("redaction_profiles.{}.{} must be a list", name, key))),
+        };
+        list.iter()
+            .map(|pointer| match pointer.as_str() {
+                Some(pointer) if pointer.starts_with('/') => Ok(pointer.to_string()),
+                _ => Err(coded(
+                    "INVALID_INPUT",
+                    format!("{} in redaction_profiles.{} is not a JSON pointer", pointer, name),
+                )),
+            })
+            .collect()
+    };
+    Ok(Profile { name: name.to_string(), drop: pointers("drop")?, hash: pointers("hash")? })
+}
+
+/// Selects the profile for this run's Output from `config.output_redaction`.
+pub fn configure(config: &Value) -> Result<()> {
+    if let Some(name) = config["output_redaction"].as_str() {
+        let _ = INLINE.set(profile(config, name)?);
+    }
+    Ok(())
+}
+
+/// Applies the configured Output profile, if any.
+pub fn apply_inline(output: &mut Value) {
+    if let Some(profile) = INLINE.get() {
+        apply(output, profile);
+    }
+}
+
+/// Hashes, invalidates and drops in that order. Every path is resolved against the document before
+/// anything is removed, so signature invalidation and `invalidated_signatures` never see shifted
+/// array indices.
+pub fn apply(document: &mut Value, profile: &Profile) -> Summary {
+    let mut summary = Summary::default();
+    let mut redacted = Vec::new();
+    for pointer in &profile.hash {
+        for path in matches(document, pointer) {
+            if let Some(value) = document.pointer_mut(&to_pointer(&path)) {
+                *value = Value::String(format!("sha256:0x{}", alloy::hex::encode(Sha256::digest(value.to_string()))));
+                summary.hashed += 1;
+                redacted.push(path);
+            }
+        }
+    }
+    let mut dropped: Vec<Vec<String>> = profile.drop.iter().flat_map(|pointer| matches(document, pointer)).collect();
+    dropped.sort_by(|a, b| compare_paths(b, a));
+    dropped.dedup();
+    // A path under another dropped path goes with it
+    let dropped: Vec<Vec<String>> = dropped
+        .iter()
+        .filter(|path| !dropped.iter().any(|other| other.len() < path.len() && path.starts_with(other)))
+        .cloned()
+        .collect();
+    redacted.extend(dropped.iter().cloned());
+
+    for path in &redacted {
+        if dropped.iter().any(|other| other.len() < path.len() && path.starts_with(other)) {
+            continue;
+        }
+        for depth in 0..path.len() {
+            let ancestor = &path[..depth];
+            let redacts_signature = path.len() == depth + 1 && path[depth] == "signature";
+            if redacts_signature || dropped.iter().any(|other| ancestor.starts_with(other)) {
+                continue;
+            }
+            let Some(Value::Object(object)) = document.pointer_mut(&to_pointer(ancestor)) else { continue };
+            if object.get("signature").is_none_or(Value::is_null) {
+                continue;
+            }
+            object.insert("signature".to_string(), Value::Null);
+            object.insert(
+                "signature_invalidated".to_string(),
+                Value::String(format!("fields under this signature were redacted by profile {}", profile.name)),
+            );
+            summary.invalidated_signatures.push(to_pointer(ancestor));
+        }
+    }
+
+    // Later array elements go first so earlier indices stay valid
+    for path in dropped {
+        let (key, parent) = path.split_last().expect("pointers have at least one token");
+        let removed = match document.pointer_mut(&to_pointer(parent)) {
+            Some(Value::Object(map)) => map.remove(key).is_some(),
+            Some(Value::Array(items)) => match key.parse::<usize>() {
+                Ok(index) if index < items.len() => {
+                    items.remove(index);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if removed {
+            summary.dropped += 1;
+        }
+    }
+    summary
+}
+
+/// Orders paths token by token, comparing array indices numerically.
+fn compare_paths(a: &[String], b: &[String]) -> std::cmp::Ordering {
+    for (x, y) in a.iter().zip(b) {
+        let order = match (x.parse::<usize>(), y.parse::<usize>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if order.is_ne() {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Concrete token paths matching `pointer`, expanding `*`.
+fn matches(document: &Value, pointer: &str) -> Vec<Vec<String>> {
+    let tokens: Vec<String> =
+        pointer.split('/').skip(1).map(|token| token.replace("~1", "/").replace("~0", "~")).collect();
+    let mut found = Vec::new();
+    collect(document, &tokens, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect(value: &Value, tokens: &[String], path: &mut Vec<String>, found: &mut Vec<Vec<String>>) {
+    let Some((token, rest)) = tokens.split_first() else {
+        found.push(path.clone());
+        return;
+    };
+    let children: Vec<(String, &Value)> = match (value, token.as_str()) {
+        (Value::Object(map), "*") => map.iter().map(|(key, child)| (key.clone(), child)).collect(),
+        (Value::Array(items), "*") => items.iter().enumerate().map(|(i, child)| (i.to_string(), child)).collect(),
+        (Value::Object(map), key) => map.get(key).map(|child| (key.to_string(), child)).into_iter().collect(),
+        (Value::Array(items), index) => index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| items.get(i))
+            .map(|child| (index.to_string(), child))
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    };
+    for (key, child) in children {
+        path.push(key);
+        collect(child, rest, path, found);
+        path.pop();
+    }
+}
+
+fn to_pointer(path: &[String]) -> String {
+    path.iter().map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1"))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn partner(drop: &[&str], hash: &[&str]) -> Profile {
+        Profile {
+            name: "partner".to_string(),
+            drop: drop.iter().map(|p| p.to_string()).collect(),
+            hash: hash.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn drops_and_hashes_with_wildcards() {
+        let mut output = json!({
+            "success": true,
+            "balance_wei": "5",
+            "user_address": "0xaa",
+            "rows": [{ "memo": "a", "amount": "1" }, { "memo": "b", "amount": "2" }]
+        });
+        let summary = apply(&mut output, &partner(&["/balance_wei", "/rows/*/memo"], &["/user_address"]));
+        assert_eq!(summary, Summary { dropped: 3, hashed: 1, invalidated_signatures: vec![] });
+        assert_eq!(output["rows"], json!([{ "amount": "1" }, { "amount": "2" }]));
+        assert!(output.get("balance_wei").is_none());
+        let hashed = output["user_address"].as_str().unwrap();
+        assert!(hashed.starts_with("sha256:0x") && hashed.len() == "sha256:0x".len() + 64);
+    }
+
+    #[test]
+    fn redacting_signed_fields_invalidates_the_signature() {
+        let mut bundle = json!({
+            "tab_id": "7",
+            "guarantees": [
+                { "claims": { "amount": "5" }, "signature": "0xsig", "metadata": { "note": "internal" } },
+                { "claims": { "amount": "6" }, "signature": "0xsig2" }
+            ]
+        });
+        let summary = apply(&mut bundle, &partner(&["/guarantees/0/metadata"], &["/guarantees/1/claims/amount"]));
+        assert_eq!(summary.invalidated_signatures, ["/guarantees/1", "/guarantees/0"]);
+        assert_eq!(bundle["guarantees"][0]["signature"], Value::Null);
+        assert!(bundle["guarantees"][1]["signature_invalidated"].as_str().unwrap().ends_with("profile partner"));
+
+        // Dropping only the signature leaves nothing stale to flag
+        let mut voucher = json!({ "claims": {}, "signature": "0xsig" });
+        assert!(apply(&mut voucher, &partner(&["/signature"], &[])).invalidated_signatures.is_empty());
+    }
+
+    #[test]
+    fn dropping_an_element_does_not_shift_invalidation_onto_its_neighbour() {
+        let mut bundle = json!({
+            "guarantees": [
+                { "claims": { "amount": "5" }, "signature": "0xsig0" },
+                { "claims": { "amount": "6" }, "signature": "0xsig1" },
+                { "claims": { "amount": "7" }, "signature": "0xsig2" }
+            ]
+        });
+        let summary = apply(&mut bundle, &partner(&["/guarantees/0"], &["/guarantees/1/claims/amount"]));
+        assert_eq!((summary.dropped, summary.hashed), (1, 1));
+        assert_eq!(summary.invalidated_signatures, ["/guarantees/1"]);
+        assert_eq!(bundle["guarantees"].as_array().unwrap().len(), 2);
+        assert_eq!(bundle["guarantees"][0]["signature"], Value::Null);
+        assert_eq!(bundle["guarantees"][1], json!({ "claims": { "amount": "7" }, "signature": "0xsig2" }));
+    }
+
+    #[test]
+    fn profiles_come_from_config() {
+        let config = json!({ "redaction_profiles": { "partner": { "drop": ["/a~1b"] } } });
+        let profile = profile(&config, "partner").unwrap();
+        assert_eq!(profile.drop, ["/a~1b"]);
+        let mut document = json!({ "a/b": 1, "c": 2 });
+        apply(&mut document, &profile);
+        assert_eq!(document, json!({ "c": 2 }));
+
+        let err = super::profile(&config, "press").unwrap_err();
+        assert_eq!(crate::error::error_code(&err).as_deref(), Some("REDACTION_PROFILE_NOT_FOUND"));
+        assert!(super::profile(&json!({ "redaction_profiles": { "x": { "drop": ["a"] } } }), "x").is_err());
+    }
+}
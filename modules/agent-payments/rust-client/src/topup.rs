@@ -0,0 +1,176 @@
+//! Automatic collateral top-up from the wallet balance, configured in `config.auto_topup`.
+//!
+//! Before a command that needs collateral runs, a wallet below
+//! `min_collateral_wei` deposits enough to reach `topup_to_wei`. The deposit is
+//! bounded by `max_per_day_wei` across this client's top-ups in the trailing
+//! 24 hours, which are recorded in `ledger_file` because each run is a separate
+//! process, and never spends below `reserve_gas_wei` of wallet balance.
+
+use crate::amount::{format_amount, parse_amount, parse_amount_or, parse_amount_str};
+use alloy::primitives::U256;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Balance kept back for gas when no `reserve_gas_wei` is configured: 0.01 ETH.
+const DEFAULT_RESERVE_GAS_WEI: u64 = 10_000_000_000_000_000;
+
+const DEFAULT_LEDGER_FILE: &str = "auto-topup-ledger.json";
+
+/// Window `max_per_day_wei` applies to.
+pub const DAY_SECONDS: u64 = 86_400;
+
+pub struct AutoTopup {
+    pub min_collateral_wei: U256,
+    pub topup_to_wei: U256,
+    pub max_per_day_wei: U256,
+    pub reserve_gas_wei: U256,
+    pub ledger_file: String,
+}
+
+/// One top-up in the ledger file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    timestamp: u64,
+    amount_wei: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Plan {
+    /// Collateral is at or above the minimum.
+    NotNeeded,
+    Deposit(U256),
+    /// Top-up was needed but the cap or wallet balance leaves nothing to deposit.
+    Blocked(&'static str),
+}
+
+impl AutoTopup {
+    pub fn from_config(config: &serde_json::Value) -> Result<Option<Self>> {
+        let policy = &config["auto_topup"];
+        if policy.is_null() || policy["enabled"] == false {
+            return Ok(None);
+        }
+        let topup = AutoTopup {
+            min_collateral_wei: parse_amount(&policy["min_collateral_wei"], "auto_topup.min_collateral_wei")?,
+            topup_to_wei: parse_amount(&policy["topup_to_wei"], "auto_topup.topup_to_wei")?,
+            max_per_day_wei: parse_amount(&policy["max_per_day_wei"], "auto_topup.max_per_day_wei")?,
+            reserve_gas_wei: parse_amount_or(
+                &policy["reserve_gas_wei"],
+                "auto_topup.reserve_gas_wei",
+                U256::from(DEFAULT_RESERVE_GAS_WEI),
+            )?,
+            ledger_file: policy["ledger_file"].as_str().unwrap_or(DEFAULT_LEDGER_FILE).to_string(),
+        };
+        if topup.topup_to_wei < topup.min_collateral_wei {
+            return Err(anyhow::anyhow!("auto_topup.topup_to_wei must be at least min_collateral_wei"));
+        }
+        Ok(Some(topup))
+    }
+
+    pub fn plan(&self, collateral: U256, wallet_balance: U256, deposited_last_day: U256) -> Plan {
+        if collateral >= self.min_collateral_wei {
+            return Plan::NotNeeded;
+        }
+        let shortfall = self.topup_to_wei - collateral;
+        let allowance = self.max_per_day_wei.saturating_sub(deposited_last_day);
+        let spendable = wallet_balance.saturating_sub(self.reserve_gas_wei);
+
+        let amount = shortfall.min(allowance).min(spendable);
+        if amount.is_zero() {
+            Plan::Blocked(if allowance.is_zero() { "daily_cap_reached" } else { "insufficient_wallet_balance" })
+        } else {
+            Plan::Deposit(amount)
+        }
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>> {
+        match fs::read_to_string(&self.ledger_file) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Corrupt auto_topup ledger {}: {}", self.ledger_file, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(anyhow::anyhow!("Failed to read auto_topup ledger {}: {}", self.ledger_file, e)),
+        }
+    }
+
+    /// Total of the recorded top-ups in the day before `now`.
+    pub fn deposited_last_day(&self, now: u64) -> Result<U256> {
+        self.entries()?
+            .iter()
+            .filter(|entry| entry.timestamp + DAY_SECONDS > now)
+            .try_fold(U256::ZERO, |total, entry| {
+                Ok(total.saturating_add(parse_amount_str(&entry.amount_wei, "auto_topup ledger amount_wei")?))
+            })
+    }
+
+    /// Adds a top-up to the ledger, dropping entries that no longer count towards the cap.
+    pub fn record(&self, now: u64, amount: U256) -> Result<()> {
+        let mut entries = self.entries()?;
+        entries.retain(|entry| entry.timestamp + DAY_SECONDS > now);
+        entries.push(Entry { timestamp: now, amount_wei: format_amount(amount) });
+        fs::write(&self.ledger_file, serde_json::to_string_pretty(&entries)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write auto_topup ledger {}: {}", self.ledger_file, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> AutoTopup {
+        AutoTopup {
+            min_collateral_wei: U256::from(100u64),
+            topup_to_wei: U256::from(500u64),
+            max_per_day_wei: U256::from(1_000u64),
+            reserve_gas_wei: U256::from(50u64),
+            ledger_file: String::new(),
+        }
+    }
+
+    #[test]
+    fn deposits_the_shortfall_to_the_target() {
+        assert_eq!(policy().plan(U256::from(100u64), U256::from(10_000u64), U256::ZERO), Plan::NotNeeded);
+        assert_eq!(
+            policy().plan(U256::from(99u64), U256::from(10_000u64), U256::ZERO),
+            Plan::Deposit(U256::from(401u64))
+        );
+    }
+
+    #[test]
+    fn respects_the_daily_cap_and_gas_reserve() {
+        assert_eq!(
+            policy().plan(U256::ZERO, U256::from(10_000u64), U256::from(800u64)),
+            Plan::Deposit(U256::from(200u64))
+        );
+        assert_eq!(
+            policy().plan(U256::ZERO, U256::from(10_000u64), U256::from(1_000u64)),
+            Plan::Blocked("daily_cap_reached")
+        );
+        assert_eq!(policy().plan(U256::ZERO, U256::from(150u64), U256::ZERO), Plan::Deposit(U256::from(100u64)));
+        assert_eq!(
+            policy().plan(U256::ZERO, U256::from(50u64), U256::ZERO),
+            Plan::Blocked("insufficient_wallet_balance")
+        );
+    }
+
+    #[test]
+    fn the_ledger_counts_only_the_last_day() {
+        let path = std::env::temp_dir().join(format!("topup-ledger-test-{}.json", std::process::id()));
+        let policy = AutoTopup { ledger_file: path.to_str().unwrap().to_string(), ..policy() };
+        assert_eq!(policy.deposited_last_day(1_000_000).unwrap(), U256::ZERO);
+
+        policy.record(1_000_000, U256::from(300u64)).unwrap();
+        policy.record(1_000_000 + DAY_SECONDS - 1, U256::from(200u64)).unwrap();
+        assert_eq!(policy.deposited_last_day(1_000_000 + DAY_SECONDS - 1).unwrap(), U256::from(500u64));
+        assert_eq!(policy.deposited_last_day(1_000_000 + DAY_SECONDS).unwrap(), U256::from(200u64));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn policy_is_optional() {
+        assert!(AutoTopup::from_config(&serde_json::json!({})).unwrap().is_none());
+        let config = serde_json::json!({
+            "auto_topup": { "min_collateral_wei": "10", "topup_to_wei": "5", "max_per_day_wei": "100" }
+        });
+        assert!(AutoTopup::from_config(&config).is_err());
+    }
+}
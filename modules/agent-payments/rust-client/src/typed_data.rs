@@ -0,0 +1,365 @@
+//! EIP-712 hashing, signing and recovery for arbitrary `eth_signTypedData_v4` payloads.
+//!
+//! Encoding follows the specification directly on the JSON payload so that
+//! recursive struct types (a type referencing itself through an array) work.
+
+use alloy::primitives::{keccak256, Address, Signature, B256, I256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+pub struct TypedData {
+    types: Map<String, Value>,
+    domain: Value,
+    primary_type: String,
+    message: Value,
+}
+
+/// Parses a typed-data payload, rejecting anything missing the four standard sections.
+pub fn parse(payload: &Value) -> Result<TypedData> {
+    for field in ["types", "domain", "primaryType", "message"] {
+        if payload.get(field).is_none() {
+            return Err(anyhow::anyhow!("Typed data is missing required field '{}'", field));
+        }
+    }
+    let mut types = payload["types"]
+        .as_object()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Typed data 'types' must be an object"))?;
+    let primary_type = payload["primaryType"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Typed data 'primaryType' must be a string"))?
+        .to_string();
+    let domain = payload["domain"].clone();
+    if !domain.is_object() {
+        return Err(anyhow::anyhow!("Typed data 'domain' must be an object"));
+    }
+
+    // Wallets infer EIP712Domain from the domain fields when it is omitted
+    if !types.contains_key("EIP712Domain") {
+        let fields: Vec<Value> = [
+            ("name", "string"),
+            ("version", "string"),
+            ("chainId", "uint256"),
+            ("verifyingContract", "address"),
+            ("salt", "bytes32"),
+        ]
+        .iter()
+        .filter(|(name, _)| domain.get(*name).is_some())
+        .map(|(name, ty)| serde_json::json!({ "name": name, "type": ty }))
+        .collect();
+        types.insert("EIP712Domain".to_string(), Value::Array(fields));
+    }
+
+    for (name, fields) in &types {
+        let fields = fields
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Type '{}' must be an array of fields", name))?;
+        for field in fields {
+            if !field["name"].is_string() || !field["type"].is_string() {
+                return Err(anyhow::anyhow!("Type '{}' has a field without a string name and type", name));
+            }
+        }
+    }
+    if !types.contains_key(&primary_type) {
+        return Err(anyhow::anyhow!("primaryType '{}' is not defined in types", primary_type));
+    }
+
+    Ok(TypedData {
+        types,
+        domain,
+        primary_type,
+        message: payload["message"].clone(),
+    })
+}
+
+pub fn digest(typed_data: &TypedData) -> Result<B256> {
+    let mut preimage = vec![0x19, 0x01];
+    preimage.extend_from_slice(typed_data.hash_struct("EIP712Domain", &typed_data.domain)?.as_slice());
+    if typed_data.primary_type != "EIP712Domain" {
+        preimage.extend_from_slice(
+            typed_data
+                .hash_struct(&typed_data.primary_type, &typed_data.message)?
+                .as_slice(),
+        );
+    }
+    Ok(keccak256(preimage))
+}
+
+/// `hashStruct(domain)` under the `EIP712Domain` type inferred from the domain's fields.
+pub fn domain_separator(domain: &Value) -> Result<B256> {
+    let typed_data = parse(&serde_json::json!({
+        "types": {},
+        "domain": domain,
+        "primaryType": "EIP712Domain",
+        "message": {}
+    }))?;
+    typed_data.hash_struct("EIP712Domain", &typed_data.domain)
+}
+
+/// Signs the digest and returns the 65-byte `r || s || v` signature with `v` in {27, 28}.
+pub fn sign(signer: &PrivateKeySigner, digest: &B256) -> Result<Signature> {
+    signer
+        .sign_hash_sync(digest)
+        .map_err(|e| anyhow::anyhow!("Failed to sign typed data: {}", e))
+}
+
+pub fn recover(signature_hex: &str, digest: &B256) -> Result<Address> {
+    let signature = Signature::from_str(signature_hex).map_err(|e| anyhow::anyhow!("Invalid signature: {}", e))?;
+    signature
+        .recover_address_from_prehash(digest)
+        .map_err(|e| anyhow::anyhow!("Failed to recover signer: {}", e))
+}
+
+impl TypedData {
+    fn fields(&self, type_name: &str) -> Result<&Vec<Value>> {
+        self.types
+            .get(type_name)
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("Unknown struct type '{}'", type_name))
+    }
+
+    fn collect_dependencies(&self, type_name: &str, found: &mut BTreeSet<String>) -> Result<()> {
+        if !found.insert(type_name.to_string()) {
+            return Ok(());
+        }
+        for field in self.fields(type_name)? {
+            let base = base_type(field["type"].as_str().unwrap_or(""));
+            if self.types.contains_key(base) {
+                self.collect_dependencies(base, found)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `encodeType`: the primary type followed by its dependencies sorted by name.
+    fn encode_type(&self, type_name: &str) -> Result<String> {
+        let mut dependencies = BTreeSet::new();
+        self.collect_dependencies(type_name, &mut dependencies)?;
+        dependencies.remove(type_name);
+
+        let mut encoded = String::new();
+        for name in std::iter::once(type_name).chain(dependencies.iter().map(String::as_str)) {
+            let members: Vec<String> = self
+                .fields(name)?
+                .iter()
+                .map(|f| format!("{} {}", f["type"].as_str().unwrap_or(""), f["name"].as_str().unwrap_or("")))
+                .collect();
+            encoded.push_str(&format!("{}({})", name, members.join(",")));
+        }
+        Ok(encoded)
+    }
+
+    fn hash_struct(&self, type_name: &str, value: &Value) -> Result<B256> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("Value for struct '{}' must be an object", type_name))?;
+
+        let mut encoded = keccak256(self.encode_type(type_name)?).to_vec();
+        for field in self.fields(type_name)? {
+            let name = field["name"].as_str().unwrap_or("");
+            let field_type = field["type"].as_str().unwrap_or("");
+            let field_value = object.get(name).unwrap_or(&Value::Null);
+            encoded.extend_from_slice(self.encode_value(field_type, field_value)?.as_slice());
+        }
+        Ok(keccak256(encoded))
+    }
+
+    fn encode_value(&self, field_type: &str, value: &Value) -> Result<B256> {
+        if let Some(element_type) = field_type.strip_suffix(']').and_then(|t| t.rsplit_once('[')) {
+            let (element_type, length) = element_type;
+            let items = value
+                .as_array()
+                .ok_or_else(|| anyhow::anyhow!("Value for '{}' must be an array", field_type))?;
+            if !length.is_empty() && length.parse::<usize>().ok() != Some(items.len()) {
+                return Err(anyhow::anyhow!("Array '{}' has {} elements", field_type, items.len()));
+            }
+            let mut encoded = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                encoded.extend_from_slice(self.encode_value(element_type, item)?.as_slice());
+            }
+            return Ok(keccak256(encoded));
+        }
+        if self.types.contains_key(field_type) {
+            return self.hash_struct(field_type, value);
+        }
+        encode_atomic(field_type, value)
+    }
+}
+
+fn base_type(field_type: &str) -> &str {
+    field_type.split('[').next().unwrap_or(field_type)
+}
+
+fn encode_atomic(field_type: &str, value: &Value) -> Result<B256> {
+    let invalid = || anyhow::anyhow!("Invalid value for '{}': {}", field_type, value);
+    match field_type {
+        "string" => Ok(keccak256(value.as_str().ok_or_else(invalid)?.as_bytes())),
+        "bytes" => Ok(keccak256(decode_hex(value.as_str().ok_or_else(invalid)?)?)),
+        "bool" => Ok(B256::from(U256::from(value.as_bool().ok_or_else(invalid)? as u8))),
+        "address" => {
+            let address = Address::from_str(value.as_str().ok_or_else(invalid)?).map_err(|_| invalid())?;
+            Ok(address.into_word())
+        }
+        _ if field_type.starts_with("uint") => Ok(B256::from(parse_uint(value).ok_or_else(invalid)?)),
+        _ if field_type.starts_with("int") => {
+            let signed = match value {
+                Value::Number(n) => n.as_i64().map(I256::try_from).and_then(|v| v.ok()),
+                Value::String(s) => I256::from_dec_str(s).ok(),
+                _ => None,
+            }
+            .ok_or_else(invalid)?;
+            Ok(B256::from(signed.into_raw()))
+        }
+        _ if field_type.starts_with("bytes") => {
+            let size: usize = field_type[5..].parse().map_err(|_| anyhow::anyhow!("Unknown type '{}'", field_type))?;
+            let bytes = decode_hex(value.as_str().ok_or_else(invalid)?)?;
+            if size == 0 || size > 32 || bytes.len() > size {
+                return Err(invalid());
+            }
+            let mut word = [0u8; 32];
+            word[..bytes.len()].copy_from_slice(&bytes);
+            Ok(B256::from(word))
+        }
+        _ => Err(anyhow::anyhow!("Unknown type '{}'", field_type)),
+    }
+}
+
+fn parse_uint(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_str_radix(s, 10).ok(),
+        },
+        _ => None,
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    alloy::hex::decode(value).map_err(|e| anyhow::anyhow!("Invalid hex '{}': {}", value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{b256, keccak256};
+
+    // The "Mail" example from the EIP-712 specification.
+    fn mail() -> serde_json::Value {
+        serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                "contents": "Hello, Bob!"
+            }
+        })
+    }
+
+    #[test]
+    fn mail_example_digest_matches_spec() {
+        let typed_data = parse(&mail()).unwrap();
+        assert_eq!(
+            digest(&typed_data).unwrap(),
+            b256!("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn domain_separator_matches_spec() {
+        assert_eq!(
+            domain_separator(&mail()["domain"]).unwrap(),
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+    }
+
+    #[test]
+    fn mail_example_signature_matches_spec() {
+        // The spec signs with keccak256("cow"), whose address is Cow's wallet.
+        let signer = PrivateKeySigner::from_bytes(&keccak256("cow")).unwrap();
+        let digest = digest(&parse(&mail()).unwrap()).unwrap();
+        let signature = sign(&signer, &digest).unwrap();
+
+        assert_eq!(
+            signature.r().to_be_bytes::<32>(),
+            b256!("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d").0
+        );
+        assert_eq!(
+            signature.s().to_be_bytes::<32>(),
+            b256!("07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562").0
+        );
+        assert_eq!(signature.as_bytes()[64], 28);
+
+        let recovered = recover(&signature.to_string(), &digest).unwrap();
+        assert_eq!(recovered, signer.address());
+        assert_eq!(
+            recovered,
+            Address::from_str("0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826").unwrap()
+        );
+    }
+
+    #[test]
+    fn recursive_and_array_types_round_trip() {
+        let payload = serde_json::json!({
+            "types": {
+                "EIP712Domain": [{ "name": "name", "type": "string" }],
+                "Node": [
+                    { "name": "label", "type": "string" },
+                    { "name": "children", "type": "Node[]" },
+                    { "name": "owners", "type": "address[]" }
+                ]
+            },
+            "primaryType": "Node",
+            "domain": { "name": "Tree" },
+            "message": {
+                "label": "root",
+                "owners": ["0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"],
+                "children": [
+                    { "label": "leaf", "children": [], "owners": [] }
+                ]
+            }
+        });
+        let signer = PrivateKeySigner::random();
+        let digest = digest(&parse(&payload).unwrap()).unwrap();
+        let signature = sign(&signer, &digest).unwrap();
+        assert_eq!(recover(&signature.to_string(), &digest).unwrap(), signer.address());
+    }
+
+    #[test]
+    fn rejects_structurally_invalid_payloads() {
+        let mut missing = mail();
+        missing.as_object_mut().unwrap().remove("domain");
+        assert!(parse(&missing).is_err());
+
+        let mut unknown_primary = mail();
+        unknown_primary["primaryType"] = serde_json::json!("Letter");
+        assert!(parse(&unknown_primary).is_err());
+    }
+}
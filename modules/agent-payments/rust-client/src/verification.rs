@@ -0,0 +1,121 @@
+//! Cross-checking transaction receipts against independently operated nodes.
+//!
+//! With `config.verify_rpc_urls` set, a chain write whose value reaches
+//! `config.verify_min_value_wei` is only reported as successful once one of the
+//! verification endpoints returns the same receipt: block hash, status and
+//! every log (address, topics, data).
+
+use crate::amount::parse_amount_or;
+use crate::chain::Chain;
+use alloy::primitives::{B256, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionReceipt;
+use anyhow::Result;
+use std::time::Duration;
+
+pub struct ReceiptVerifier {
+    urls: Vec<String>,
+    pub min_value_wei: U256,
+    timeout: Duration,
+}
+
+pub enum Verdict {
+    Match(serde_json::Value),
+    Mismatch(serde_json::Value),
+    /// No verification endpoint produced the receipt before the timeout.
+    Unavailable(String),
+}
+
+impl ReceiptVerifier {
+    pub fn from_config(config: &serde_json::Value) -> Result<Option<Self>> {
+        let urls: Vec<String> = config["verify_rpc_urls"]
+            .as_array()
+            .map(|urls| urls.iter().filter_map(|url| url.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        if urls.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ReceiptVerifier {
+            urls,
+            min_value_wei: parse_amount_or(&config["verify_min_value_wei"], "verify_min_value_wei", U256::ZERO)?,
+            timeout: Duration::from_secs(config["verify_timeout_seconds"].as_u64().unwrap_or(60)),
+        }))
+    }
+
+    /// Polls the endpoints in order until one has the receipt, then compares it with the primary's.
+    pub async fn verify(&self, primary: &TransactionReceipt) -> Verdict {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut last_error = String::from("receipt not yet available");
+        loop {
+            for url in &self.urls {
+                match fetch_receipt(url, primary.transaction_hash).await {
+                    Ok(Some(receipt)) => return compare(url, primary, &receipt),
+                    Ok(None) => last_error = format!("{} has no receipt yet", url),
+                    Err(e) => last_error = format!("{}: {}", url, e),
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Verdict::Unavailable(last_error);
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    }
+}
+
+/// Value a write moved: the ETH sent with the transaction.
+pub async fn transaction_value(chain: &Chain, receipt: &TransactionReceipt) -> Result<U256> {
+    Ok(chain
+        .provider
+        .get_transaction_by_hash(receipt.transaction_hash)
+        .await?
+        .map(|tx| alloy::consensus::Transaction::value(&tx))
+        .unwrap_or_default())
+}
+
+async fn fetch_receipt(url: &str, tx_hash: B256) -> Result<Option<TransactionReceipt>> {
+    let provider = ProviderBuilder::new().connect_client(crate::wirelog::rpc_client(url)?);
+    Ok(provider.get_transaction_receipt(tx_hash).await?)
+}
+
+fn fingerprint(receipt: &TransactionReceipt) -> serde_json::Value {
+    let logs: Vec<serde_json::Value> = receipt
+        .inner
+        .logs()
+        .iter()
+        .map(|log| {
+            serde_json::json!({
+                "address": log.address(),
+                "topics": log.topics(),
+                "data": log.data().data
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "block_hash": receipt.block_hash,
+        "block_number": receipt.block_number,
+        "status": receipt.status(),
+        "logs": logs
+    })
+}
+
+fn compare(url: &str, primary: &TransactionReceipt, verifier: &TransactionReceipt) -> Verdict {
+    let primary = fingerprint(primary);
+    let verifier = fingerprint(verifier);
+    let mismatched: Vec<&str> = ["block_hash", "block_number", "status", "logs"]
+        .into_iter()
+        .filter(|field| primary[field] != verifier[field])
+        .collect();
+
+    let details = serde_json::json!({
+        "endpoint": url,
+        "matches": mismatched.is_empty(),
+        "mismatched_fields": mismatched,
+        "primary": primary,
+        "verifier": verifier
+    });
+    if mismatched.is_empty() {
+        Verdict::Match(details)
+    } else {
+        Verdict::Mismatch(details)
+    }
+}
@@ -0,0 +1,101 @@
+//! Portable payment vouchers: signed claims in one self-describing file.
+//!
+//! A voucher carries everything the recipient side needs to request a
+//! guarantee — the claims, the SDK's signature and scheme, and the signer —
+//! so the user and recipient flows can run on different machines without
+//! shuttling fragments that drift apart.
+//!
+//! The SDK does not expose the digest it signs, so this client cannot check
+//! the payment signature itself; the 4Mica API does that when the guarantee is
+//! issued. Instead the signer seals the voucher: an EIP-191 signature over the
+//! claims, payment signature and scheme, which any later edit invalidates.
+
+use crate::error::coded;
+use alloy::primitives::{Address, Signature};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::str::FromStr;
+
+pub const FORMAT: &str = "4mica-payment-voucher";
+pub const VERSION: u64 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Voucher {
+    pub format: String,
+    pub version: u64,
+    pub claims: serde_json::Value,
+    /// Payment signature returned by the SDK's `sign_payment`.
+    pub signature: String,
+    pub scheme: String,
+    pub signer: String,
+    /// EIP-191 signature by `signer` over [`seal_message`].
+    pub seal: String,
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// Ties the signature to the delivered work; see [`crate::workbinding`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub work_binding: Option<crate::workbinding::WorkBinding>,
+}
+
+/// Reads a voucher from `args.voucher` or the file at `args.voucher_file`, if either is given.
+pub fn from_args(args: &serde_json::Value) -> Result<Option<Voucher>> {
+    let value = match (&args["voucher"], args["voucher_file"].as_str()) {
+        (serde_json::Value::Null, None) => return Ok(None),
+        (serde_json::Value::Null, Some(path)) => {
+            let content = fs::read_to_string(path)
+                .map_err(|e| coded("INVALID_VOUCHER", format!("Failed to read voucher file {}: {}", path, e)))?;
+            serde_json::from_str(&content)
+                .map_err(|e| coded("INVALID_VOUCHER", format!("Voucher file {} is not JSON: {}", path, e)))?
+        }
+        (voucher, _) => voucher.clone(),
+    };
+
+    if value["format"] != FORMAT {
+        return Err(coded("INVALID_VOUCHER", format!("Not a payment voucher: format must be \"{}\"", FORMAT)));
+    }
+    if value["version"] != VERSION {
+        return Err(coded(
+            "UNSUPPORTED_VOUCHER_VERSION",
+            format!("Voucher version {} is not supported; this client reads version {}", value["version"], VERSION),
+        ));
+    }
+    let voucher = serde_json::from_value(value).map_err(|e| coded("INVALID_VOUCHER", format!("Invalid voucher: {}", e)))?;
+    Ok(Some(voucher))
+}
+
+/// Claim fields covered by the seal, in the order they are written into it.
+const SEALED_CLAIMS: [&str; 6] = ["user_address", "recipient_address", "tab_id", "req_id", "amount", "timestamp"];
+
+fn seal_message(claims: &serde_json::Value, signature: &str, scheme: &str) -> String {
+    let mut message = "4Mica payment voucher".to_string();
+    for field in SEALED_CLAIMS {
+        message.push_str(&format!("\n{}: {}", field, claims[field]));
+    }
+    message.push_str(&format!("\nsignature: {}\nscheme: {}", signature.to_ascii_lowercase(), scheme));
+    message
+}
+
+pub fn seal(signer: &PrivateKeySigner, claims: &serde_json::Value, signature: &str, scheme: &str) -> Result<String> {
+    Ok(signer.sign_message_sync(seal_message(claims, signature, scheme).as_bytes())?.to_string())
+}
+
+/// Checks that the voucher's signer sealed exactly its claims, signature and scheme.
+pub fn check_seal(voucher: &Voucher) -> Result<()> {
+    let signer = Address::from_str(&voucher.signer)
+        .map_err(|e| coded("INVALID_VOUCHER", format!("Invalid voucher signer {}: {}", voucher.signer, e)))?;
+    let message = seal_message(&voucher.claims, &voucher.signature, &voucher.scheme);
+    let recovered = Signature::from_str(&voucher.seal)
+        .ok()
+        .and_then(|seal| seal.recover_address_from_msg(message.as_bytes()).ok());
+    if recovered != Some(signer) {
+        return Err(coded(
+            "VOUCHER_TAMPERED",
+            format!("Voucher seal is not {}'s signature over its claims and payment signature", signer),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! Verification of signed webhook deliveries.
+//!
+//! A delivery carries `X-Signature: t=<unix seconds>,v1=<hex>` where each `v1`
+//! is HMAC-SHA256 over `"<t>.<raw body>"` with one of the endpoint's secrets.
+//! Senders rotating a secret include one `v1` per active secret, and receivers
+//! accept a match against any secret they hold, so both sides can overlap old
+//! and new secrets. Deliveries whose timestamp falls outside the tolerance
+//! window are rejected so a captured request cannot be replayed later.
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const DEFAULT_TOLERANCE_SECONDS: u64 = 300;
+
+pub struct Verification {
+    pub valid: bool,
+    pub timestamp: Option<u64>,
+    /// Index into the secrets list of the secret that matched.
+    pub secret_index: Option<usize>,
+    pub reason: Option<&'static str>,
+}
+
+impl Verification {
+    fn rejected(timestamp: Option<u64>, reason: &'static str) -> Self {
+        Verification {
+            valid: false,
+            timestamp,
+            secret_index: None,
+            reason: Some(reason),
+        }
+    }
+}
+
+pub fn verify(header: &str, body: &[u8], secrets: &[String], now: u64, tolerance_seconds: u64) -> Result<Verification> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => {
+                timestamp = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| anyhow::anyhow!("Invalid signature timestamp '{}'", value))?,
+                )
+            }
+            Some(("v1", value)) => signatures.push(
+                alloy::hex::decode(value).map_err(|e| anyhow::anyhow!("Invalid v1 signature '{}': {}", value, e))?,
+            ),
+            // Unknown schemes are skipped so newer senders stay verifiable
+            _ => {}
+        }
+    }
+
+    let Some(timestamp) = timestamp else {
+        return Ok(Verification::rejected(None, "missing_timestamp"));
+    };
+    if signatures.is_empty() {
+        return Ok(Verification::rejected(Some(timestamp), "missing_signature"));
+    }
+    if now.abs_diff(timestamp) > tolerance_seconds {
+        return Ok(Verification::rejected(Some(timestamp), "timestamp_outside_tolerance"));
+    }
+
+    for (index, secret) in secrets.iter().enumerate() {
+        for signature in &signatures {
+            if mac(secret, timestamp, body)?.verify_slice(signature).is_ok() {
+                return Ok(Verification {
+                    valid: true,
+                    timestamp: Some(timestamp),
+                    secret_index: Some(index),
+                    reason: None,
+                });
+            }
+        }
+    }
+    Ok(Verification::rejected(Some(timestamp), "signature_mismatch"))
+}
+
+fn mac(secret: &str, timestamp: u64, body: &[u8]) -> Result<HmacSha256> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid webhook secret: {}", e))?;
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(secrets: &[&str], timestamp: u64, body: &[u8]) -> String {
+        let mut header = format!("t={}", timestamp);
+        for secret in secrets {
+            let signature = mac(secret, timestamp, body).unwrap().finalize().into_bytes();
+            header.push_str(&format!(",v1={}", alloy::hex::encode(signature)));
+        }
+        header
+    }
+
+    fn secrets(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_a_fresh_signature() {
+        let body = br#"{"event":"tab.paid"}"#;
+        let result = verify(&header(&["s1"], 1_000, body), body, &secrets(&["s1"]), 1_010, 300).unwrap();
+        assert!(result.valid);
+        assert_eq!(result.secret_index, Some(0));
+    }
+
+    #[test]
+    fn rejects_replays_and_tampering() {
+        let body = br#"{"event":"tab.paid"}"#;
+        let signed = header(&["s1"], 1_000, body);
+        let replayed = verify(&signed, body, &secrets(&["s1"]), 1_000 + 301, 300).unwrap();
+        assert_eq!(replayed.reason, Some("timestamp_outside_tolerance"));
+        let tampered = verify(&signed, br#"{"event":"tab.unpaid"}"#, &secrets(&["s1"]), 1_000, 300).unwrap();
+        assert_eq!(tampered.reason, Some("signature_mismatch"));
+        let wrong_secret = verify(&signed, body, &secrets(&["s2"]), 1_000, 300).unwrap();
+        assert!(!wrong_secret.valid);
+    }
+
+    #[test]
+    fn overlapping_secrets_verify_during_rotation() {
+        let body = b"payload";
+        let during_rotation = header(&["old", "new"], 50, body);
+        assert_eq!(verify(&during_rotation, body, &secrets(&["new"]), 50, 300).unwrap().secret_index, Some(0));
+        let after_rotation = header(&["new"], 50, body);
+        assert_eq!(verify(&after_rotation, body, &secrets(&["old", "new"]), 50, 300).unwrap().secret_index, Some(1));
+    }
+}
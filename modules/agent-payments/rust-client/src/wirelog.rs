@@ -0,0 +1,240 @@
+//! Outbound RPC logging for correlating our calls with a provider's logs.
+//!
+//! With `config.wire_log` set, every JSON-RPC call made through [`rpc_client`]
+//! is logged at debug level (target `wire`) as one JSON line: method, endpoint,
+//! SHA-256 of the request body, response status and latency. `wire_log.file`
+//! mirrors the lines to a file that is rotated to `<file>.1` once it reaches
+//! `max_file_bytes`. The digest lets both sides match a request without the
+//! log holding signatures; endpoint path segments long enough to be API keys
+//! are masked. Full bodies need `log_bodies_unsafe: true` and are only turned
+//! on by [`allow_bodies`] once the chain is known not to be mainnet.
+//!
+//! The SDK owns the HTTP client it uses for the 4Mica API, so those calls are
+//! logged at their call sites through [`api_call`]: one line per SDK call
+//! (which may span several HTTP requests), with the API host as endpoint and
+//! the digest taken over the call's arguments as this client passes them,
+//! serialized as JSON, rather than over the SDK's request body. Transactions
+//! the SDK sends through its own Ethereum provider are not covered.
+
+use alloy::rpc::client::{ClientBuilder, RpcClient};
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportFut};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+
+/// Rotation size when `wire_log.max_file_bytes` is not set: 10 MiB.
+const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Path segments at least this long are treated as credentials.
+const MASKED_SEGMENT_LEN: usize = 20;
+
+struct WireLog {
+    api_endpoint: String,
+    file: Option<String>,
+    max_file_bytes: u64,
+    bodies_requested: bool,
+}
+
+static WIRE_LOG: OnceLock<WireLog> = OnceLock::new();
+static LOG_BODIES: AtomicBool = AtomicBool::new(false);
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn configure(config: &serde_json::Value) {
+    let wire_log = &config["wire_log"];
+    if wire_log.is_null() || wire_log["enabled"] == false {
+        return;
+    }
+    let api_endpoint = config["rpc_url"]
+        .as_str()
+        .unwrap_or(crate::DEFAULT_API_URL)
+        .parse()
+        .map(|url| masked_endpoint(&url))
+        .unwrap_or_default();
+    let _ = WIRE_LOG.set(WireLog {
+        api_endpoint,
+        file: wire_log["file"].as_str().map(str::to_string),
+        max_file_bytes: wire_log["max_file_bytes"].as_u64().unwrap_or(DEFAULT_MAX_FILE_BYTES),
+        bodies_requested: wire_log["log_bodies_unsafe"].as_bool().unwrap_or(false),
+    });
+}
+
+pub fn bodies_requested() -> bool {
+    WIRE_LOG.get().is_some_and(|wire_log| wire_log.bodies_requested)
+}
+
+/// Turns on the requested body logging, refusing on mainnet (chain id 1).
+pub fn allow_bodies(chain_id: u64) -> Result<()> {
+    if chain_id == 1 {
+        return Err(crate::error::coded(
+            "UNSAFE_LOGGING_REFUSED",
+            "wire_log.log_bodies_unsafe is refused on mainnet; request bodies contain signatures",
+        ));
+    }
+    LOG_BODIES.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// An HTTP JSON-RPC client that logs through the wire log when one is configured.
+pub fn rpc_client(url: &str) -> Result<RpcClient> {
+    let url: alloy::transports::http::reqwest::Url = url.parse()?;
+    if WIRE_LOG.get().is_none() {
+        return Ok(ClientBuilder::default().http(url));
+    }
+    let layer = WireLogLayer {
+        endpoint: masked_endpoint(&url),
+    };
+    Ok(ClientBuilder::default().layer(layer).http(url))
+}
+
+/// Runs one SDK call to the 4Mica API, logging it like an RPC call when the wire log is on.
+pub async fn api_call<T, E>(
+    method: &str,
+    args: serde_json::Value,
+    call: impl std::future::Future<Output = std::result::Result<T, E>>,
+) -> std::result::Result<T, E> {
+    let Some(wire_log) = WIRE_LOG.get() else {
+        return call.await;
+    };
+    let body = args.to_string();
+    let mut entry = serde_json::json!({
+        "method": method,
+        "endpoint": wire_log.api_endpoint,
+        "body_sha256": alloy::hex::encode(Sha256::digest(body.as_bytes())),
+    });
+    if LOG_BODIES.load(Ordering::Relaxed) {
+        entry["body"] = serde_json::Value::String(body);
+    }
+
+    let started = Instant::now();
+    let result = call.await;
+    entry["status"] = serde_json::json!(if result.is_ok() { "ok" } else { "api_error" });
+    entry["latency_ms"] = serde_json::json!(started.elapsed().as_millis() as u64);
+    record(entry);
+    result
+}
+
+fn masked_endpoint(url: &alloy::transports::http::reqwest::Url) -> String {
+    let path: Vec<&str> = url
+        .path()
+        .split('/')
+        .map(|segment| if segment.len() >= MASKED_SEGMENT_LEN { "***" } else { segment })
+        .collect();
+    format!("{}{}", url.host_str().unwrap_or(""), path.join("/"))
+}
+
+fn record(entry: serde_json::Value) {
+    let line = entry.to_string();
+    log::debug!(target: "wire", "{}", line);
+    let Some(wire_log) = WIRE_LOG.get() else { return };
+    let Some(path) = &wire_log.file else { return };
+    let _guard = FILE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    // Logging must never fail the call it describes
+    let _ = append_rotating(path, &line, wire_log.max_file_bytes);
+}
+
+fn append_rotating(path: &str, line: &str, max_file_bytes: u64) -> std::io::Result<()> {
+    let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 + 1 > max_file_bytes {
+        fs::rename(path, format!("{}.1", path))?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+#[derive(Clone)]
+struct WireLogLayer {
+    endpoint: String,
+}
+
+impl<S> Layer<S> for WireLogLayer {
+    type Service = WireLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WireLogService {
+            inner,
+            endpoint: self.endpoint.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WireLogService<S> {
+    inner: S,
+    endpoint: String,
+}
+
+impl<S> Service<RequestPacket> for WireLogService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError, Future = TransportFut<'static>>,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let methods: Vec<String> = request.method_names().map(str::to_string).collect();
+        let body = serde_json::to_vec(&request).unwrap_or_default();
+        let mut entry = serde_json::json!({
+            "method": methods.join(","),
+            "endpoint": self.endpoint,
+            "body_sha256": alloy::hex::encode(Sha256::digest(&body)),
+        });
+        if LOG_BODIES.load(Ordering::Relaxed) {
+            entry["body"] = serde_json::Value::String(String::from_utf8_lossy(&body).into_owned());
+        }
+
+        let started = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let result = response.await;
+            entry["status"] = serde_json::json!(match &result {
+                Ok(response) if response.is_error() => "rpc_error",
+                Ok(_) => "ok",
+                Err(_) => "transport_error",
+            });
+            entry["latency_ms"] = serde_json::json!(started.elapsed().as_millis() as u64);
+            record(entry);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_mask_key_like_path_segments() {
+        let url = "https://eth-sepolia.g.alchemy.com/v2/abcdefghijklmnopqrstuvwxyz012345".parse().unwrap();
+        assert_eq!(masked_endpoint(&url), "eth-sepolia.g.alchemy.com/v2/***");
+        let url = "http://localhost:8545/".parse().unwrap();
+        assert_eq!(masked_endpoint(&url), "localhost/");
+    }
+
+    #[test]
+    fn wire_log_file_rotates_by_size() {
+        let path = std::env::temp_dir().join(format!("wirelog-test-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(format!("{}.1", path));
+
+        append_rotating(path, "first", 12).unwrap();
+        append_rotating(path, "second", 12).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "second\n");
+        assert_eq!(fs::read_to_string(format!("{}.1", path)).unwrap(), "first\n");
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(format!("{}.1", path)).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+//! Binding a payment to the agent output it pays for.
+//!
+//! The payment claims are fixed by the contract and have no room for a
+//! reference to the delivered work, so the binding travels beside them: the
+//! payer signs (EIP-191) a message naming its payment signature and the
+//! SHA-256 of the delivered artifact. Anyone holding the artifact can hash it
+//! and check the binding without trusting whoever relayed the voucher.
+
+use crate::error::coded;
+use alloy::primitives::{Address, Signature, B256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkBinding {
+    /// SHA-256 of the delivered artifact, `0x`-prefixed.
+    pub work_hash: String,
+    /// EIP-191 signature by the payer over [`message`].
+    pub signature: String,
+}
+
+/// Why a binding does not hold; kept apart from failures of the payment signature itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The binding is not signed by the payer of this payment signature.
+    BindingSignature,
+    /// The binding is genuine but names different work.
+    WorkHash,
+}
+
+impl Mismatch {
+    pub fn code(self) -> &'static str {
+        match self {
+            Mismatch::BindingSignature => "WORK_BINDING_SIGNATURE_INVALID",
+            Mismatch::WorkHash => "WORK_HASH_MISMATCH",
+        }
+    }
+}
+
+pub fn hash_artifact(bytes: &[u8]) -> B256 {
+    B256::from_slice(&Sha256::digest(bytes))
+}
+
+pub fn parse_work_hash(text: &str) -> Result<B256> {
+    B256::from_str(text).map_err(|e| coded("INVALID_WORK_HASH", format!("work_hash '{}' is not a 32-byte hex hash: {}", text, e)))
+}
+
+fn message(payment_signature: &str, work_hash: B256) -> String {
+    format!(
+        "4Mica work binding\npayment signature: {}\nwork hash: {}",
+        payment_signature.to_ascii_lowercase(),
+        work_hash
+    )
+}
+
+pub fn bind(signer: &PrivateKeySigner, payment_signature: &str, work_hash: B256) -> Result<WorkBinding> {
+    let signature = signer.sign_message_sync(message(payment_signature, work_hash).as_bytes())?;
+    Ok(WorkBinding {
+        work_hash: work_hash.to_string(),
+        signature: signature.to_string(),
+    })
+}
+
+/// Checks that `payer` bound `payment_signature` to `delivered`.
+pub fn check(binding: &WorkBinding, payment_signature: &str, payer: Address, delivered: B256) -> Result<(), Mismatch> {
+    let bound = parse_work_hash(&binding.work_hash).map_err(|_| Mismatch::BindingSignature)?;
+    let recovered = Signature::from_str(&binding.signature)
+        .ok()
+        .and_then(|signature| signature.recover_address_from_msg(message(payment_signature, bound).as_bytes()).ok());
+    if recovered != Some(payer) {
+        return Err(Mismatch::BindingSignature);
+    }
+    if bound != delivered {
+        return Err(Mismatch::WorkHash);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_check_payer_and_work_separately() {
+        let payer = PrivateKeySigner::random();
+        let delivered = hash_artifact(b"agent output");
+        let binding = bind(&payer, "0xABCD", delivered).unwrap();
+
+        assert_eq!(check(&binding, "0xabcd", payer.address(), delivered), Ok(()));
+        assert_eq!(check(&binding, "0xabcd", payer.address(), hash_artifact(b"other output")), Err(Mismatch::WorkHash));
+        assert_eq!(check(&binding, "0xabce", payer.address(), delivered), Err(Mismatch::BindingSignature));
+        assert_eq!(
+            check(&binding, "0xabcd", PrivateKeySigner::random().address(), delivered),
+            Err(Mismatch::BindingSignature)
+        );
+
+        let relabelled = WorkBinding { work_hash: hash_artifact(b"other output").to_string(), ..binding };
+        assert_eq!(check(&relabelled, "0xabcd", payer.address(), delivered), Err(Mismatch::BindingSignature));
+    }
+}
@@ -0,0 +1,35 @@
+//! Runs the binary over hostile Input files and checks that each one produces a
+//! well-formed error Output instead of a panic or a missing file.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn hostile_inputs_produce_error_outputs() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let out_dir = std::env::temp_dir().join(format!("fourmica-corpus-{}", std::process::id()));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let mut entries: Vec<_> = fs::read_dir(&corpus).unwrap().map(|e| e.unwrap().path()).collect();
+    entries.sort();
+    assert!(!entries.is_empty(), "corpus is empty");
+
+    for input in entries {
+        let name = input.file_name().unwrap().to_string_lossy().to_string();
+        let output = out_dir.join(&name);
+        let status = Command::new(env!("CARGO_BIN_EXE_fourmica-client"))
+            .arg(&input)
+            .arg(&output)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{}: exited with {}", name, status);
+
+        let written = fs::read_to_string(&output).unwrap_or_else(|e| panic!("{}: no output: {}", name, e));
+        let written: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(written["success"], false, "{}: {}", name, written);
+        assert!(written["error"].is_string(), "{}: {}", name, written);
+    }
+
+    fs::remove_dir_all(&out_dir).ok();
+}